@@ -1,12 +1,134 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Discriminator;
 
 declare_id!("Gx3muwmBzRr8DVvyPdW46PNbT815TGcVqSf7q1WUeHwj");
 
+/// How long an access token from `generate_access_token` stays valid
+const ACCESS_TOKEN_TTL_SECS: i64 = 60 * 60;
+
+/// Bumped whenever an account layout changes incompatibly
+const ACCOUNT_VERSION: u8 = 2;
+
+/// Capability bits reported by `program_info`, one per capability a client
+/// has to know about before using it: an instruction family, an event
+/// setting or an auxiliary account. Bits are only ever appended, in the
+/// order capabilities were added, and never reused. Everything is compiled
+/// into every build, so the mask tells clients which program version they
+/// are talking to. Single-account reads every version with `program_info`
+/// has (`event_exists`, `get_claim_status`, `can_join` and the like) carry
+/// no bit, and neither do maintenance instructions such as
+/// `reconcile_count`. Sweeping expired claims is part of `CLAIM_EXPIRY`.
+pub const FEATURE_ALLOWLIST: u32 = 1 << 0;
+pub const FEATURE_TRACKS: u32 = 1 << 1;
+pub const FEATURE_BLOCKLIST: u32 = 1 << 2;
+pub const FEATURE_ATTESTATION: u32 = 1 << 3;
+pub const FEATURE_COMPRESSED_CLAIMS: u32 = 1 << 4;
+pub const FEATURE_TEMPLATES: u32 = 1 << 5;
+pub const FEATURE_GUESTS: u32 = 1 << 6;
+pub const FEATURE_CLAIM_EXPIRY: u32 = 1 << 7;
+pub const FEATURE_HYBRID_ATTENDANCE: u32 = 1 << 8;
+pub const FEATURE_SPONSORED_RENT: u32 = 1 << 9;
+pub const FEATURE_ATTENDANCE_CPI: u32 = 1 << 10;
+pub const FEATURE_ARCHIVING: u32 = 1 << 11;
+pub const FEATURE_CLAIM_PROOFS: u32 = 1 << 12;
+pub const FEATURE_AGENDA: u32 = 1 << 13;
+pub const FEATURE_METADATA_LOCK: u32 = 1 << 14;
+pub const FEATURE_UNLIMITED: u32 = 1 << 15;
+pub const FEATURE_FEEDBACK: u32 = 1 << 16;
+pub const FEATURE_ACCESS_TOKENS: u32 = 1 << 17;
+pub const FEATURE_OWNERSHIP_TRANSFER: u32 = 1 << 18;
+pub const FEATURE_CLONING: u32 = 1 << 19;
+pub const FEATURE_EDIT_HISTORY: u32 = 1 << 20;
+pub const FEATURE_CHANNELS: u32 = 1 << 21;
+pub const FEATURE_CREATOR_TOTALS: u32 = 1 << 22;
+pub const FEATURE_OVERBOOKING: u32 = 1 << 23;
+pub const FEATURE_BADGES: u32 = 1 << 24;
+pub const FEATURE_DISPLAY_PRICE: u32 = 1 << 25;
+pub const FEATURE_IMPORTED_CLAIMS: u32 = 1 << 26;
+pub const FEATURE_AGE_RESTRICTION: u32 = 1 << 27;
+pub const FEATURE_CREATE_AND_JOIN: u32 = 1 << 28;
+pub const FEATURE_IMMUTABLE_CLAIMS: u32 = 1 << 29;
+pub const FEATURE_MODE_FLAGS: u32 = 1 << 30;
+
+const SUPPORTED_FEATURES: u32 = FEATURE_ALLOWLIST
+    | FEATURE_TRACKS
+    | FEATURE_BLOCKLIST
+    | FEATURE_ATTESTATION
+    | FEATURE_COMPRESSED_CLAIMS
+    | FEATURE_TEMPLATES
+    | FEATURE_GUESTS
+    | FEATURE_CLAIM_EXPIRY
+    | FEATURE_HYBRID_ATTENDANCE
+    | FEATURE_SPONSORED_RENT
+    | FEATURE_ATTENDANCE_CPI
+    | FEATURE_ARCHIVING
+    | FEATURE_CLAIM_PROOFS
+    | FEATURE_AGENDA
+    | FEATURE_METADATA_LOCK
+    | FEATURE_UNLIMITED
+    | FEATURE_FEEDBACK
+    | FEATURE_ACCESS_TOKENS
+    | FEATURE_OWNERSHIP_TRANSFER
+    | FEATURE_CLONING
+    | FEATURE_EDIT_HISTORY
+    | FEATURE_CHANNELS
+    | FEATURE_CREATOR_TOTALS
+    | FEATURE_OVERBOOKING
+    | FEATURE_BADGES
+    | FEATURE_DISPLAY_PRICE
+    | FEATURE_IMPORTED_CLAIMS
+    | FEATURE_AGE_RESTRICTION
+    | FEATURE_CREATE_AND_JOIN
+    | FEATURE_IMMUTABLE_CLAIMS
+    | FEATURE_MODE_FLAGS;
+
+/// `Event::mode_flags` bits, one per optional behavior an event has on
+pub const MODE_VIRTUAL: u32 = 1 << 0;
+pub const MODE_ALLOWLIST: u32 = 1 << 1;
+pub const MODE_BLOCKLIST: u32 = 1 << 2;
+pub const MODE_TRACK: u32 = 1 << 3;
+pub const MODE_ATTESTATION: u32 = 1 << 4;
+pub const MODE_COMPRESSED_CLAIMS: u32 = 1 << 5;
+pub const MODE_CLAIM_EXPIRY: u32 = 1 << 6;
+pub const MODE_HYBRID_CAPS: u32 = 1 << 7;
+pub const MODE_GUESTS: u32 = 1 << 8;
+pub const MODE_SPONSORED_RENT: u32 = 1 << 9;
+pub const MODE_UNLIMITED: u32 = 1 << 10;
+pub const MODE_CHANNELS: u32 = 1 << 11;
+pub const MODE_OVERBOOKING: u32 = 1 << 12;
+pub const MODE_AGE_RESTRICTED: u32 = 1 << 13;
+pub const MODE_AGENDA: u32 = 1 << 14;
+pub const MODE_IMMUTABLE_CLAIMS: u32 = 1 << 15;
+
+/// Most claims a single batch instruction will process
+const MAX_BATCH: usize = 10;
+
+/// Most an event may overbook by, in basis points of `max_claims`
+const MAX_OVERBOOK_BPS: u16 = 5_000;
+
+/// `AttendeeClaim::attendance_type` values
+pub const ATTENDANCE_IN_PERSON: u8 = 0;
+pub const ATTENDANCE_VIRTUAL: u8 = 1;
+
+/// `AttendeeProfile::badge_level` values
+pub const BADGE_NONE: u8 = 0;
+pub const BADGE_BRONZE: u8 = 1;
+pub const BADGE_SILVER: u8 = 2;
+pub const BADGE_GOLD: u8 = 3;
+
+/// Events joined from one creator needed for Bronze, Silver and Gold
+const BADGE_THRESHOLDS: [u32; 3] = [3, 10, 25];
+
+/// Attendee counts that trigger a `MilestoneReached` event
+const MILESTONES: [u16; 10] = [25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000];
+
 #[program]
 pub mod solmeet {
     use super::*;
 
     /// Create a new event on-chain
+    #[allow(clippy::too_many_arguments)]
     pub fn create_event(
         ctx: Context<CreateEvent>,
         event_id: String,
@@ -15,141 +137,3439 @@ pub mod solmeet {
         venue: String,
         date: String,
         max_claims: u16,
+        full_redirect_uri: Option<String>,
+        claim_ttl_secs: i64,
+        is_virtual: bool,
+        stream_uri: String,
+        unlimited: bool,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        init_event(
+            event,
+            ctx.accounts.creator.key(),
+            event_id,
+            name,
+            description,
+            venue,
+            date,
+            max_claims,
+            full_redirect_uri,
+            claim_ttl_secs,
+            is_virtual,
+            stream_uri,
+            unlimited,
+        )?;
+
+        // Count the event towards today's creation stats
+        let daily_stats = &mut ctx.accounts.daily_stats;
+        daily_stats.day = DailyStats::current_day()?;
+        daily_stats.events_created = daily_stats.events_created.saturating_add(1);
+
+        msg!("Created event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Update an event's details. Only the creator can do this, and any
+    /// field left as `None` is kept as is.
+    pub fn update_event(
+        ctx: Context<UpdateEventDetails>,
+        _event_id: String,
+        name: Option<String>,
+        description: Option<String>,
+        venue: Option<String>,
+        date: Option<String>,
+        organizer_memo: Option<String>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let history = &mut ctx.accounts.history;
+        let editor = ctx.accounts.creator.key();
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        // Locked events only accept changes to the organizer memo
+        let edits_metadata =
+            name.is_some() || description.is_some() || venue.is_some() || date.is_some();
+        require!(
+            !(edits_metadata && event.metadata_locked),
+            ErrorCode::MetadataLocked
+        );
+
+        history.event = event.key();
+        if let Some(name) = name {
+            require!(name.len() <= 50, ErrorCode::NameTooLong);
+            event.name = name;
+            history.record(EventField::Name, timestamp, editor)?;
+        }
+        if let Some(description) = description {
+            require!(description.len() <= 200, ErrorCode::DescriptionTooLong);
+            event.description = description;
+            history.record(EventField::Description, timestamp, editor)?;
+        }
+        if let Some(venue) = venue {
+            require!(venue.len() <= 100, ErrorCode::VenueTooLong);
+            require!(
+                event.is_virtual || !venue.is_empty(),
+                ErrorCode::VenueRequired
+            );
+            event.venue = venue;
+            history.record(EventField::Venue, timestamp, editor)?;
+        }
+        if let Some(date) = date {
+            require!(date.len() <= 30, ErrorCode::DateTooLong);
+            event.date = date;
+            history.record(EventField::Date, timestamp, editor)?;
+        }
+        if let Some(organizer_memo) = organizer_memo {
+            require!(organizer_memo.len() <= 100, ErrorCode::MemoTooLong);
+            event.organizer_memo = organizer_memo;
+        }
+
+        msg!("Updated event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Join an existing event
+    pub fn join_event(
+        ctx: Context<JoinEvent>,
+        _event_id: String,
+        ticket_number: Option<u32>,
+        agenda_hash: Option<[u8; 32]>,
+        attendance_type: u8,
+        channel_id: Option<String>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let claim = &mut ctx.accounts.claim;
+        let attendee = &ctx.accounts.attendee;
+
+        // Verify event exists (this is implicit since we're using the event as an account)
+        require!(!event.archived, ErrorCode::EventArchived);
+        require!(!event.compressed_claims, ErrorCode::CompressedClaimsOnly);
+
+        // Someone other than the attendee may only cover the claim rent when
+        // the creator sponsors the event and is the one paying
+        let payer = &ctx.accounts.payer;
+        if payer.key() != attendee.key() {
+            require!(
+                event.creator_pays_rent && payer.key() == event.creator,
+                ErrorCode::SponsorshipNotEnabled
+            );
+        }
+        
+        // Ticketed events only admit holders of an unused ticket number
+        if event.has_allowlist {
+            let allowlist = ctx
+                .accounts
+                .allowlist
+                .as_mut()
+                .ok_or(ErrorCode::TicketNotAllowed)?;
+            let ticket_number = ticket_number.ok_or(ErrorCode::TicketNotAllowed)?;
+            allowlist.consume(ticket_number)?;
+        }
+
+        // Banned wallets can't join
+        if event.has_blocklist {
+            let blocklist = ctx
+                .accounts
+                .blocklist
+                .as_ref()
+                .ok_or(ErrorCode::AttendeeBlocked)?;
+            require!(
+                !blocklist.banned.contains(&attendee.key()),
+                ErrorCode::AttendeeBlocked
+            );
+        }
+
+        // Anti-sybil events need the device attestation service to co-sign
+        // the join, which covers both the attendee and the event accounts
+        if let Some(expected) = event.attestation_authority {
+            let attestor = ctx
+                .accounts
+                .attestation_authority
+                .as_ref()
+                .ok_or(ErrorCode::MissingAttestation)?;
+            require_keys_eq!(attestor.key(), expected, ErrorCode::MissingAttestation);
+        }
+
+        // Age-restricted events need the age verifier to co-sign, attesting
+        // the attendee is old enough without the age ever going on-chain
+        if event.min_age > 0 {
+            let verifier = ctx
+                .accounts
+                .age_verifier
+                .as_ref()
+                .ok_or(ErrorCode::AgeNotVerified)?;
+            require!(
+                event.age_verifier == Some(verifier.key()),
+                ErrorCode::AgeNotVerified
+            );
+        }
+
+        // The client must echo the agenda hash it displayed to the attendee
+        if let Some(expected) = event.agenda_hash {
+            require!(
+                agenda_hash == Some(expected),
+                ErrorCode::AgendaNotAcknowledged
+            );
+        }
+
+        // Tracks may only be joined by attendees of the parent event whose
+        // claim is still valid
+        if event.requires_parent_claim {
+            let parent_event = ctx
+                .accounts
+                .parent_event
+                .as_ref()
+                .ok_or(ErrorCode::ParentClaimRequired)?;
+            let parent_claim = ctx
+                .accounts
+                .parent_claim
+                .as_ref()
+                .ok_or(ErrorCode::ParentClaimRequired)?;
+            require!(
+                event.parent_event == Some(parent_event.key())
+                    && parent_claim.key()
+                        == claim_address(&parent_event.event_id, &attendee.key(), ctx.program_id)
+                    && parent_claim.attendee == attendee.key()
+                    && !parent_claim.is_expired(Clock::get()?.unix_timestamp),
+                ErrorCode::ParentClaimRequired
+            );
+        }
+
+        // Check if max claims has been reached, overall and for this
+        // attendance type
+        event.admit()?;
+        event.admit_attendance_type(attendance_type)?;
+        let channel = event.admit_channel(channel_id.as_deref())?;
+
+        issue_claim(event, claim, attendee.key(), attendance_type, channel)?;
+
+        // Count the join towards the attendee's standing with the creator
+        ctx.accounts
+            .profile
+            .record_join(event.creator, attendee.key());
+        claim.credited_creator = Some(event.creator);
+
+        msg!("New attendee joined event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Verify an attendee's claim on behalf of another program (via CPI)
+    ///
+    /// Expected accounts, in order:
+    /// 1. `event`    - event PDA, seeds `["event", event_id]`
+    /// 2. `claim`    - claim PDA, seeds `["claim", event_id, attendee]` (may be uninitialized)
+    /// 3. `attendee` - the wallet whose attendance is being checked
+    ///
+    /// Returns `true` through return data when the claim exists and belongs
+    /// to the attendee for this event, `false` otherwise.
+    pub fn verify_attendance_cpi(
+        ctx: Context<VerifyAttendance>,
+        _event_id: String,
+    ) -> Result<bool> {
+        // An unclaimed PDA is simply "not attended", not an error
+        let claim = match AttendeeClaim::try_load(&ctx.accounts.claim, ctx.program_id) {
+            Some(claim) => claim,
+            None => return Ok(false),
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        Ok(claim.attendee == ctx.accounts.attendee.key() && !claim.is_expired(now))
+    }
+
+    /// Restrict an event to pre-assigned ticket numbers. Bit `n` of
+    /// `allowlist_bitmap` (LSB first within each byte) marks ticket `n` as issued.
+    pub fn create_allowlist(
+        ctx: Context<CreateAllowlist>,
+        _event_id: String,
+        allowlist_bitmap: Vec<u8>,
+    ) -> Result<()> {
+        require!(
+            allowlist_bitmap.len() <= Allowlist::MAX_BYTES,
+            ErrorCode::AllowlistTooLarge
+        );
+
+        let event = &mut ctx.accounts.event;
+        let allowlist = &mut ctx.accounts.allowlist;
+
+        allowlist.event = event.key();
+        allowlist.consumed_bitmap = vec![0; allowlist_bitmap.len()];
+        allowlist.allowlist_bitmap = allowlist_bitmap;
+        event.has_allowlist = true;
+        event.refresh_mode_flags();
+
+        msg!("Created allowlist for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Permanently freeze the event's name, description, venue and date so
+    /// attendees know they won't change (creator only). Joins stay open.
+    pub fn lock_metadata(ctx: Context<UpdateEvent>, _event_id: String) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.metadata_locked = true;
+
+        msg!("Locked metadata for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Ban a wallet from joining the event (creator only). Existing claims
+    /// are not affected.
+    pub fn block_attendee(
+        ctx: Context<ManageBlocklist>,
+        _event_id: String,
+        attendee: Pubkey,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let blocklist = &mut ctx.accounts.blocklist;
+
+        blocklist.event = event.key();
+        if !blocklist.banned.contains(&attendee) {
+            require!(
+                blocklist.banned.len() < Blocklist::MAX_ENTRIES,
+                ErrorCode::BlocklistFull
+            );
+            blocklist.banned.push(attendee);
+        }
+        event.has_blocklist = true;
+        event.refresh_mode_flags();
+
+        msg!("Blocked {} from event {}", attendee, event.event_id);
+        Ok(())
+    }
+
+    /// Lift a ban (creator only)
+    pub fn unblock_attendee(
+        ctx: Context<ManageBlocklist>,
+        _event_id: String,
+        attendee: Pubkey,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let blocklist = &mut ctx.accounts.blocklist;
+
+        blocklist.event = event.key();
+        blocklist.banned.retain(|banned| *banned != attendee);
+        // Once nobody is banned, joins no longer need the blocklist account
+        event.has_blocklist = !blocklist.banned.is_empty();
+        event.refresh_mode_flags();
+
+        msg!("Unblocked {} from event {}", attendee, event.event_id);
+        Ok(())
+    }
+
+    /// Hide an event from active listings and stop new joins (creator only)
+    pub fn archive_event(ctx: Context<UpdateEvent>, _event_id: String) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.archived = true;
+
+        msg!("Archived event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Bring an archived event back (creator only)
+    pub fn unarchive_event(ctx: Context<UpdateEvent>, _event_id: String) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.archived = false;
+
+        msg!("Unarchived event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Create a track (sub-event) under an existing event. Tracks are
+    /// ordinary events joined through `join_event`; with
+    /// `requires_parent_claim` set, only attendees of the parent may join.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_track(
+        ctx: Context<CreateTrack>,
+        _parent_event_id: String,
+        track_id: String,
+        name: String,
+        description: String,
+        venue: String,
+        date: String,
+        max_claims: u16,
+        requires_parent_claim: bool,
+    ) -> Result<()> {
+        let parent = &ctx.accounts.parent_event;
+        let track = &mut ctx.accounts.track;
+
+        // Tracks are held the same way as their parent
+        init_event(
+            track,
+            parent.creator,
+            track_id,
+            name,
+            description,
+            venue,
+            date,
+            max_claims,
+            None,
+            0,
+            parent.is_virtual,
+            parent.stream_uri.clone(),
+            false,
+        )?;
+
+        track.parent_event = Some(parent.key());
+        track.requires_parent_claim = requires_parent_claim;
+        track.refresh_mode_flags();
+
+        let daily_stats = &mut ctx.accounts.daily_stats;
+        daily_stats.day = DailyStats::current_day()?;
+        daily_stats.events_created = daily_stats.events_created.saturating_add(1);
+
+        msg!(
+            "Created track {} under event {}",
+            track.event_id,
+            parent.event_id
+        );
+        Ok(())
+    }
+
+    /// Choose whether the creator covers claim rent for attendees (creator only).
+    /// Sponsored joins are `join_event` calls with the creator as `payer`.
+    pub fn set_creator_pays_rent(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        creator_pays_rent: bool,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.creator_pays_rent = creator_pays_rent;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Event {} claim rent paid by {}",
+            event.event_id,
+            if creator_pays_rent {
+                "creator"
+            } else {
+                "attendees"
+            }
+        );
+        Ok(())
+    }
+
+    /// Cheap probe for whether an event exists, returned through return data.
+    /// `event` is the PDA for `event_id` and may be uninitialized.
+    pub fn event_exists(ctx: Context<EventExists>, _event_id: String) -> Result<bool> {
+        let info = &ctx.accounts.event;
+        if info.owner != ctx.program_id {
+            return Ok(false);
+        }
+
+        let data = info.try_borrow_data()?;
+        Ok(data.get(..8) == Some(&Event::DISCRIMINATOR[..]))
+    }
+
+    /// Set or clear the device-attestation authority that must co-sign every
+    /// join (creator only)
+    pub fn set_attestation_authority(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        attestation_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.attestation_authority = attestation_authority;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Updated attestation authority for event: {}",
+            event.event_id
+        );
+        Ok(())
+    }
+
+    /// Restrict joins to attendees of at least `min_age`, as attested by a
+    /// co-signature from `age_verifier` (creator only). A `min_age` of 0
+    /// lifts the restriction.
+    pub fn set_age_restriction(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        min_age: u8,
+        age_verifier: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            min_age == 0 || age_verifier.is_some(),
+            ErrorCode::AgeNotVerified
+        );
+
+        let event = &mut ctx.accounts.event;
+        event.min_age = min_age;
+        event.age_verifier = if min_age == 0 { None } else { age_verifier };
+        event.refresh_mode_flags();
+
+        msg!("Event {} minimum age: {}", event.event_id, min_age);
+        Ok(())
+    }
+
+    /// Set or clear the agenda attendees must acknowledge when joining
+    /// (creator only). Claims made earlier keep their acknowledgment state.
+    pub fn set_agenda_hash(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        agenda_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.agenda_hash = agenda_hash;
+        event.refresh_mode_flags();
+
+        msg!("Updated agenda for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Switch an event to compressed claims (creator only, before anyone has
+    /// joined). Claims then become leaves of a Merkle tree of the given depth
+    /// instead of individual `AttendeeClaim` accounts, and `join_event` is
+    /// disabled.
+    pub fn enable_compressed_claims(
+        ctx: Context<EnableCompressedClaims>,
+        _event_id: String,
+        depth: u8,
+    ) -> Result<()> {
+        require!(
+            depth > 0 && depth <= ClaimTree::MAX_DEPTH,
+            ErrorCode::InvalidTreeDepth
+        );
+
+        let event = &mut ctx.accounts.event;
+        require!(event.claims_count == 0, ErrorCode::EventHasClaims);
+
+        let tree = &mut ctx.accounts.claim_tree;
+        tree.event = event.key();
+        tree.depth = depth;
+        tree.next_index = 0;
+        tree.filled_subtrees = vec![[0; 32]; depth as usize];
+
+        event.compressed_claims = true;
+        event.claims_root = ClaimTree::empty_root(depth);
+        event.refresh_mode_flags();
+
+        msg!("Enabled compressed claims for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Append a compressed claim for `attendee` (creator only).
+    ///
+    /// Without a per-attendee account the program can't tell that a wallet
+    /// already holds a claim, so compressed claims are issued by the
+    /// organizer, whose backend is responsible for de-duplicating attendees.
+    /// Indexers rebuild the tree from the `CompressedClaimAdded` events.
+    pub fn append_compressed_claim(
+        ctx: Context<AppendCompressedClaim>,
+        _event_id: String,
+        attendee: Pubkey,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let tree = &mut ctx.accounts.claim_tree;
+        require!(!event.archived, ErrorCode::EventArchived);
+
+        event.admit()?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let leaf = compressed_claim_leaf(&attendee, &event.event_id, timestamp);
+        let index = tree.next_index;
+        event.claims_root = tree.append(leaf)?;
+
+        emit!(CompressedClaimAdded {
+            event_id: event.event_id.clone(),
+            attendee,
+            timestamp,
+            index,
+        });
+
+        msg!("New compressed claim for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Check a compressed claim against the event's current root, returned
+    /// through return data. `proof` lists sibling hashes from the leaf up,
+    /// one per tree level, and must be built against the latest root.
+    pub fn verify_compressed_claim(
+        ctx: Context<VerifyCompressedClaim>,
+        _event_id: String,
+        attendee: Pubkey,
+        timestamp: i64,
+        index: u32,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<bool> {
+        let event = &ctx.accounts.event;
+        if !event.compressed_claims {
+            return Ok(false);
+        }
+
+        // A short proof would verify an interior node rather than a leaf
+        let tree = &ctx.accounts.claim_tree;
+        if proof.len() != tree.depth as usize || index >= tree.next_index {
+            return Ok(false);
+        }
+
+        let leaf = compressed_claim_leaf(&attendee, &event.event_id, timestamp);
+        Ok(merkle_root_from_proof(leaf, index, &proof) == event.claims_root)
+    }
+
+    /// Lift or restore the event's capacity cap (creator only). When
+    /// restoring, `max_claims` optionally sets the new cap, which can't be
+    /// below the number of claims already made.
+    pub fn set_unlimited(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        unlimited: bool,
+        max_claims: Option<u16>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+
+        if !unlimited {
+            let cap = max_claims.unwrap_or(event.max_claims);
+            require!(cap > 0, ErrorCode::InvalidMaxClaims);
+            require!(
+                cap >= event.claims_count,
+                ErrorCode::CannotShrinkBelowClaims
+            );
+            event.max_claims = cap;
+        }
+        event.unlimited = unlimited;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Event {} capacity: {}",
+            event.event_id,
+            if unlimited {
+                "unlimited".to_string()
+            } else {
+                event.max_claims.to_string()
+            }
+        );
+        Ok(())
+    }
+
+    /// Start handing the event to `new_owner` (creator only). Control moves
+    /// only once the new owner accepts.
+    pub fn propose_ownership(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.pending_owner = Some(new_owner);
+
+        msg!(
+            "Proposed {} as owner of event {}",
+            new_owner,
+            event.event_id
+        );
+        Ok(())
+    }
+
+    /// Complete a transfer; must be signed by the proposed owner
+    pub fn accept_ownership(ctx: Context<AcceptOwnership>, _event_id: String) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let new_owner = ctx.accounts.new_owner.key();
+
+        let pending_owner = event.pending_owner.ok_or(ErrorCode::NoPendingOwner)?;
+        require_keys_eq!(pending_owner, new_owner, ErrorCode::NotPendingOwner);
+
+        event.creator = new_owner;
+        event.pending_owner = None;
+
+        msg!("{} is now the owner of event {}", new_owner, event.event_id);
+        Ok(())
+    }
+
+    /// Withdraw a pending proposal (creator only)
+    pub fn cancel_ownership_proposal(ctx: Context<UpdateEvent>, _event_id: String) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        require!(event.pending_owner.is_some(), ErrorCode::NoPendingOwner);
+        event.pending_owner = None;
+
+        msg!("Cancelled ownership proposal for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Create a new occurrence of one of the caller's events, copying its
+    /// details and settings under a fresh ID and date. Counters (including
+    /// per-channel usage) start from zero; allowlists, blocklists, guest
+    /// claims and compressed claims are per event and are not copied.
+    pub fn clone_event(
+        ctx: Context<CloneEvent>,
+        _source_event_id: String,
+        new_event_id: String,
+        new_date: String,
+    ) -> Result<()> {
+        let source = &ctx.accounts.source_event;
+        let event = &mut ctx.accounts.event;
+        init_event(
+            event,
+            source.creator,
+            new_event_id,
+            source.name.clone(),
+            source.description.clone(),
+            source.venue.clone(),
+            new_date,
+            source.max_claims,
+            source.full_redirect_uri.clone(),
+            source.claim_ttl_secs,
+            source.is_virtual,
+            source.stream_uri.clone(),
+            source.unlimited,
+        )?;
+
+        event.organizer_memo = source.organizer_memo.clone();
+        event.creator_pays_rent = source.creator_pays_rent;
+        event.agenda_hash = source.agenda_hash;
+        event.attestation_authority = source.attestation_authority;
+        event.min_age = source.min_age;
+        event.age_verifier = source.age_verifier;
+        event.overbook_bps = source.overbook_bps;
+        event.in_person_cap = source.in_person_cap;
+        event.virtual_cap = source.virtual_cap;
+        event.guest_allowance = source.guest_allowance;
+        event.channels = source
+            .channels
+            .iter()
+            .map(|channel| Channel {
+                id: channel.id.clone(),
+                cap: channel.cap,
+                used: 0,
+            })
+            .collect();
+        event.display_currency = source.display_currency.clone();
+        event.display_price = source.display_price;
+        event.immutable_after_secs = source.immutable_after_secs;
+        event.refresh_mode_flags();
+
+        let daily_stats = &mut ctx.accounts.daily_stats;
+        daily_stats.day = DailyStats::current_day()?;
+        daily_stats.events_created = daily_stats.events_created.saturating_add(1);
+
+        msg!("Cloned event {} into {}", source.event_id, event.event_id);
+        Ok(())
+    }
+
+    /// Set separate in-person and virtual capacities for hybrid events
+    /// (creator only); 0 means that type is only bound by `max_claims`
+    pub fn set_attendance_caps(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        in_person_cap: u16,
+        virtual_cap: u16,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        require!(
+            (in_person_cap == 0 || in_person_cap >= event.in_person_count)
+                && (virtual_cap == 0 || virtual_cap >= event.virtual_count),
+            ErrorCode::CannotShrinkBelowClaims
+        );
+
+        event.in_person_cap = in_person_cap;
+        event.virtual_cap = virtual_cap;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Event {} caps: {} in person, {} virtual",
+            event.event_id,
+            in_person_cap,
+            virtual_cap
+        );
+        Ok(())
+    }
+
+    /// Save reusable event defaults under `template_id` (per creator)
+    pub fn create_template(
+        ctx: Context<CreateTemplate>,
+        template_id: String,
+        settings: TemplateSettings,
+    ) -> Result<()> {
+        require!(
+            !template_id.is_empty() && template_id.len() <= 16,
+            ErrorCode::InvalidTemplateId
+        );
+        settings.validate()?;
+
+        let template = &mut ctx.accounts.template;
+        template.creator = ctx.accounts.creator.key();
+        template.template_id = template_id;
+        template.settings = settings;
+
+        msg!("Created template: {}", template.template_id);
+        Ok(())
+    }
+
+    /// Replace a template's defaults. Events already created from it keep
+    /// the settings they were created with.
+    pub fn update_template(
+        ctx: Context<UpdateTemplate>,
+        _template_id: String,
+        settings: TemplateSettings,
+    ) -> Result<()> {
+        settings.validate()?;
+
+        let template = &mut ctx.accounts.template;
+        template.settings = settings;
+
+        msg!("Updated template: {}", template.template_id);
+        Ok(())
+    }
+
+    /// Create an event using one of the caller's templates for everything
+    /// except its ID, name and date
+    pub fn create_event_from_template(
+        ctx: Context<CreateEventFromTemplate>,
+        _template_id: String,
+        event_id: String,
+        name: String,
+        date: String,
+    ) -> Result<()> {
+        let template = EventTemplate::try_load(&ctx.accounts.template, ctx.program_id)
+            .ok_or(ErrorCode::TemplateNotFound)?;
+        let settings = template.settings;
+        let creator = ctx.accounts.creator.key();
+        let event = &mut ctx.accounts.event;
+        init_event(
+            event,
+            creator,
+            event_id,
+            name,
+            settings.description,
+            settings.venue,
+            date,
+            settings.max_claims,
+            settings.full_redirect_uri,
+            settings.claim_ttl_secs,
+            settings.is_virtual,
+            settings.stream_uri,
+            settings.unlimited,
+        )?;
+
+        event.creator_pays_rent = settings.creator_pays_rent;
+        event.attestation_authority = settings.attestation_authority;
+        event.refresh_mode_flags();
+
+        let daily_stats = &mut ctx.accounts.daily_stats;
+        daily_stats.day = DailyStats::current_day()?;
+        daily_stats.events_created = daily_stats.events_created.saturating_add(1);
+
+        msg!(
+            "Created event {} from template {}",
+            event.event_id,
+            template.template_id
+        );
+        Ok(())
+    }
+
+    /// Let each attendee bring up to `guest_allowance` guests (creator only)
+    pub fn set_guest_allowance(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        guest_allowance: u8,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.guest_allowance = guest_allowance;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Event {} guest allowance: {}",
+            event.event_id,
+            guest_allowance
+        );
+        Ok(())
+    }
+
+    /// Register a guest under the signing attendee's claim. Guests take a
+    /// seat like any attendee and receive their own serial number.
+    pub fn register_guest(
+        ctx: Context<RegisterGuest>,
+        _event_id: String,
+        guest_name: String,
     ) -> Result<()> {
+        require!(guest_name.len() <= 50, ErrorCode::GuestNameTooLong);
+
         let event = &mut ctx.accounts.event;
-        let creator = &ctx.accounts.creator;
+        let host_claim = &mut ctx.accounts.host_claim;
+        let guest_claim = &mut ctx.accounts.guest_claim;
+
+        require!(!event.archived, ErrorCode::EventArchived);
+        let now = Clock::get()?.unix_timestamp;
+        require!(!host_claim.is_expired(now), ErrorCode::ClaimExpired);
+        require!(
+            !event.is_claim_immutable(host_claim, now),
+            ErrorCode::ClaimImmutable
+        );
+        require!(
+            host_claim.guests_registered < event.guest_allowance,
+            ErrorCode::GuestAllowanceExceeded
+        );
+
+        // Guests sit under the host's attendance type and channel
+        event.admit()?;
+        event.admit_attendance_type(host_claim.attendance_type)?;
+        if let Some(index) = host_claim.channel {
+            event.take_channel_seat(index)?;
+        }
+
+        guest_claim.event = event.key();
+        guest_claim.host = host_claim.attendee;
+        guest_claim.guest_name = guest_name;
+        guest_claim.timestamp = now;
+        guest_claim.serial = event.next_serial;
+
+        event.next_serial = event
+            .next_serial
+            .checked_add(1)
+            .ok_or(ErrorCode::SerialOverflow)?;
+        host_claim.guests_registered += 1;
+
+        msg!("Registered guest for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Describe this deployment through return data so clients can
+    /// feature-detect instead of hardcoding assumptions
+    pub fn program_info(_ctx: Context<GetProgramInfo>) -> Result<ProgramInfo> {
+        Ok(ProgramInfo {
+            program_id: crate::ID,
+            account_version: ACCOUNT_VERSION,
+            supported_features: SUPPORTED_FEATURES,
+        })
+    }
+
+    /// Add a registration channel (web, partner, ...) with its own cap
+    /// (creator only). Once an event has channels, every join must name one.
+    pub fn add_channel(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        channel_id: String,
+        cap: u16,
+    ) -> Result<()> {
+        require!(
+            !channel_id.is_empty() && channel_id.len() <= Channel::MAX_ID_LEN,
+            ErrorCode::InvalidChannel
+        );
+        require!(cap > 0, ErrorCode::InvalidMaxClaims);
+
+        let event = &mut ctx.accounts.event;
+        require!(
+            event.channels.len() < Channel::MAX_CHANNELS,
+            ErrorCode::TooManyChannels
+        );
+        require!(
+            !event
+                .channels
+                .iter()
+                .any(|channel| channel.id == channel_id),
+            ErrorCode::InvalidChannel
+        );
+
+        msg!(
+            "Added channel {} ({} seats) to event {}",
+            channel_id,
+            cap,
+            event.event_id
+        );
+        event.channels.push(Channel {
+            id: channel_id,
+            cap,
+            used: 0,
+        });
+        event.refresh_mode_flags();
+        Ok(())
+    }
+
+    /// Rewrite a claim still in the original `Claim` layout as an
+    /// `AttendeeClaim`. Anyone may call this; `payer` covers any extra rent
+    /// the new layout needs, and rent it frees goes to the attendee.
+    /// Baseline claims, which predate serial numbers, get the next serial,
+    /// a position commitment and a place in the attendance-type counters.
+    pub fn migrate_claim(ctx: Context<MigrateClaim>, _event_id: String) -> Result<()> {
+        let info = ctx.accounts.claim.to_account_info();
+        require_keys_eq!(*info.owner, *ctx.program_id, ErrorCode::NotLegacyClaim);
+
+        let event = &mut ctx.accounts.event;
+        let legacy =
+            LegacyClaim::decode(&info.try_borrow_data()?).ok_or(ErrorCode::NotLegacyClaim)?;
+        require!(
+            legacy.event_id == event.event_id && legacy.attendee == ctx.accounts.attendee.key(),
+            ErrorCode::NotLegacyClaim
+        );
+
+        let mut claim = AttendeeClaim {
+            attendee: legacy.attendee,
+            timestamp: legacy.timestamp,
+            slot: legacy.slot,
+            expires_at: legacy.expires_at,
+            serial: legacy.serial,
+            acknowledged: legacy.acknowledged,
+            feedback_hash: legacy.feedback_hash,
+            position: legacy.position,
+            position_commitment: legacy.position_commitment,
+            attendance_type: legacy.attendance_type,
+            guests_registered: legacy.guests_registered,
+            channel: legacy.channel,
+            imported: false,
+            credited_creator: None,
+        };
+
+        // Serials start at 1, so 0 means the claim was made before serials,
+        // positions and attendance types were recorded
+        if claim.serial == 0 {
+            claim.serial = event.next_serial;
+            event.next_serial = event
+                .next_serial
+                .checked_add(1)
+                .ok_or(ErrorCode::SerialOverflow)?;
+            claim.position_commitment =
+                position_commitment(&event.key(), &claim.attendee, claim.position, claim.slot);
+            event.recount(&claim);
+        }
+
+        // Baseline claims are smaller than the new layout and grow; claims
+        // that still carry the full event ID and padding shrink
+        let new_len = 8 + AttendeeClaim::SPACE;
+        let rent = Rent::get()?.minimum_balance(new_len);
+        let lamports = info.lamports();
+        if lamports < rent {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                rent - lamports,
+            )?;
+        } else {
+            **info.try_borrow_mut_lamports()? -= lamports - rent;
+            **ctx.accounts.attendee.try_borrow_mut_lamports()? += lamports - rent;
+        }
+
+        info.realloc(new_len, false)?;
+        {
+            let mut data = info.try_borrow_mut_data()?;
+            data.fill(0);
+            claim.try_serialize(&mut &mut data[..])?;
+        }
+
+        msg!(
+            "Migrated claim of {} for event {}",
+            legacy.attendee,
+            legacy.event_id
+        );
+        Ok(())
+    }
+
+    /// Totals across a creator's events for dashboards, returned through
+    /// return data. Events are passed as remaining accounts; anything that
+    /// isn't an event owned by `creator` is skipped, as are repeats and
+    /// archived events.
+    pub fn get_creator_totals(
+        ctx: Context<GetCreatorTotals>,
+        creator: Pubkey,
+    ) -> Result<CreatorTotals> {
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        let mut totals = CreatorTotals {
+            total_events: 0,
+            total_attendees: 0,
+        };
+
+        for info in ctx.remaining_accounts {
+            let event = match Event::try_load(info, ctx.program_id) {
+                Some(event) if event.creator == creator && !event.archived => event,
+                _ => continue,
+            };
+            if seen.contains(info.key) {
+                continue;
+            }
+            seen.push(info.key());
+
+            totals.total_events += 1;
+            totals.total_attendees += u64::from(event.claims_count);
+        }
+
+        Ok(totals)
+    }
+
+    /// Accept up to `overbook_bps` basis points more registrations than
+    /// `max_claims` for events where many registrants don't show (creator
+    /// only). Can't drop capacity below the claims already made.
+    pub fn set_overbook_bps(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        overbook_bps: u16,
+    ) -> Result<()> {
+        require!(overbook_bps <= MAX_OVERBOOK_BPS, ErrorCode::InvalidBps);
+
+        let event = &mut ctx.accounts.event;
+        event.overbook_bps = overbook_bps;
+        event.refresh_mode_flags();
+        require!(
+            event.unlimited || event.capacity() >= event.claims_count,
+            ErrorCode::CannotShrinkBelowClaims
+        );
+
+        msg!(
+            "Event {} accepts {} registrations for {} seats",
+            event.event_id,
+            event.capacity(),
+            event.max_claims
+        );
+        Ok(())
+    }
+
+    /// Set the price front-ends show for the event (creator only). These are
+    /// display hints only: nothing on-chain charges or checks against them.
+    pub fn set_display_price(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        display_currency: String,
+        display_price: u64,
+    ) -> Result<()> {
+        require!(
+            display_currency.len() <= 8,
+            ErrorCode::DisplayCurrencyTooLong
+        );
+
+        let event = &mut ctx.accounts.event;
+        event.display_currency = display_currency;
+        event.display_price = display_price;
+
+        msg!("Updated display price for event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Seed an event with attendees from an off-chain system (creator only,
+    /// who pays the rent). Each entry becomes a claim with its historical
+    /// timestamp, which can't be in the future, and `imported` set;
+    /// remaining accounts are the claim PDAs in the same order. Imports count
+    /// against capacity but skip join checks, and don't count towards
+    /// attendee badges.
+    pub fn import_claims<'info>(
+        ctx: Context<'_, '_, '_, 'info, ImportClaims<'info>>,
+        event_id: String,
+        entries: Vec<ImportEntry>,
+    ) -> Result<()> {
+        require!(entries.len() <= MAX_BATCH, ErrorCode::BatchTooLarge);
+        require!(
+            ctx.remaining_accounts.len() == entries.len(),
+            ErrorCode::BatchAccountsMismatch
+        );
+
+        let event = &mut ctx.accounts.event;
+        require!(!event.archived, ErrorCode::EventArchived);
+        require!(!event.compressed_claims, ErrorCode::CompressedClaimsOnly);
+
+        let clock = Clock::get()?;
+        let attendance_type = if event.is_virtual {
+            ATTENDANCE_VIRTUAL
+        } else {
+            ATTENDANCE_IN_PERSON
+        };
+
+        for (entry, info) in entries.iter().zip(ctx.remaining_accounts) {
+            let (address, bump) = Pubkey::find_program_address(
+                &[b"claim", event_id.as_bytes(), entry.attendee.as_ref()],
+                ctx.program_id,
+            );
+            require_keys_eq!(info.key(), address, ErrorCode::BatchAccountsMismatch);
+            require!(info.data_is_empty(), ErrorCode::AlreadyJoined);
+            require!(
+                entry.timestamp <= clock.unix_timestamp,
+                ErrorCode::ImportTimestampInFuture
+            );
+
+            event.admit()?;
+            event.admit_attendance_type(attendance_type)?;
+
+            create_pda(
+                &ctx.accounts.creator.to_account_info(),
+                info,
+                &ctx.accounts.system_program.to_account_info(),
+                8 + AttendeeClaim::SPACE,
+                ctx.program_id,
+                &[
+                    b"claim",
+                    event_id.as_bytes(),
+                    entry.attendee.as_ref(),
+                    &[bump],
+                ],
+            )?;
+
+            let claim = AttendeeClaim {
+                attendee: entry.attendee,
+                timestamp: entry.timestamp,
+                slot: clock.slot,
+                expires_at: if event.claim_ttl_secs == 0 {
+                    0
+                } else {
+                    entry
+                        .timestamp
+                        .checked_add(event.claim_ttl_secs)
+                        .ok_or(ErrorCode::InvalidClaimTtl)?
+                },
+                serial: event.next_serial,
+                acknowledged: false,
+                feedback_hash: None,
+                position: event.claims_count,
+                position_commitment: position_commitment(
+                    &event.key(),
+                    &entry.attendee,
+                    event.claims_count,
+                    clock.slot,
+                ),
+                attendance_type,
+                guests_registered: 0,
+                channel: None,
+                imported: true,
+                credited_creator: None,
+            };
+            claim.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+            event.next_serial = event
+                .next_serial
+                .checked_add(1)
+                .ok_or(ErrorCode::SerialOverflow)?;
+        }
+
+        msg!(
+            "Imported {} claims into event {}",
+            entries.len(),
+            event.event_id
+        );
+        Ok(())
+    }
+
+    /// Create an event and register the creator as its first attendee in one
+    /// transaction, for demos and testing the join flow. Takes the same
+    /// arguments as `create_event` plus the creator's attendance type.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_and_join(
+        ctx: Context<CreateAndJoin>,
+        event_id: String,
+        name: String,
+        description: String,
+        venue: String,
+        date: String,
+        max_claims: u16,
+        full_redirect_uri: Option<String>,
+        claim_ttl_secs: i64,
+        is_virtual: bool,
+        stream_uri: String,
+        unlimited: bool,
+        attendance_type: u8,
+    ) -> Result<()> {
+        let creator = ctx.accounts.creator.key();
+        let event = &mut ctx.accounts.event;
+        init_event(
+            event,
+            creator,
+            event_id,
+            name,
+            description,
+            venue,
+            date,
+            max_claims,
+            full_redirect_uri,
+            claim_ttl_secs,
+            is_virtual,
+            stream_uri,
+            unlimited,
+        )?;
+
+        let daily_stats = &mut ctx.accounts.daily_stats;
+        daily_stats.day = DailyStats::current_day()?;
+        daily_stats.events_created = daily_stats.events_created.saturating_add(1);
+
+        // A fresh event has none of the gates `join_event` checks, only
+        // its capacity
+        event.admit()?;
+        event.admit_attendance_type(attendance_type)?;
+        issue_claim(
+            event,
+            &mut ctx.accounts.claim,
+            creator,
+            attendance_type,
+            None,
+        )?;
+        ctx.accounts.profile.record_join(creator, creator);
+        ctx.accounts.claim.credited_creator = Some(creator);
+
+        msg!("Created and joined event: {}", event.event_id);
+        Ok(())
+    }
+
+    /// Make claims immutable once they are `immutable_after_secs` old, so
+    /// attendance records can no longer be changed or closed (creator
+    /// only). 0 keeps claims mutable. Once set, the window can only be
+    /// shortened, so claims that became immutable stay that way.
+    pub fn set_immutable_after(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        immutable_after_secs: i64,
+    ) -> Result<()> {
+        require!(
+            immutable_after_secs >= 0,
+            ErrorCode::InvalidImmutabilityWindow
+        );
+
+        let event = &mut ctx.accounts.event;
+        require!(
+            event.immutable_after_secs == 0
+                || (immutable_after_secs != 0
+                    && immutable_after_secs <= event.immutable_after_secs),
+            ErrorCode::ImmutabilityWindowLoosened
+        );
+        event.immutable_after_secs = immutable_after_secs;
+        event.refresh_mode_flags();
+
+        msg!(
+            "Event {} claims become immutable after {}s",
+            event.event_id,
+            immutable_after_secs
+        );
+        Ok(())
+    }
+
+    /// The event's `MODE_*` flags, returned through return data, so clients
+    /// can tell which behaviors it uses without decoding every field
+    pub fn get_event_modes(ctx: Context<GetEvent>, _event_id: String) -> Result<u32> {
+        Ok(ctx.accounts.event.mode_flags)
+    }
+
+    /// Number of claims still available, returned through return data
+    pub fn get_claims_remaining(ctx: Context<GetEvent>, _event_id: String) -> Result<u32> {
+        Ok(ctx.accounts.event.claims_remaining())
+    }
+
+    /// Overwrite `claims_count` with the true number of claims (creator only).
+    ///
+    /// If claim accounts are passed as remaining accounts they are counted
+    /// on-chain and must add up to `actual`, and the attendance-type and
+    /// channel counters are rebuilt from them too. Guests hold seats, so
+    /// their `GuestClaim` accounts are counted alongside attendee claims and
+    /// need their host's claim passed as well. Without claim accounts the
+    /// creator's count is taken as given, which only events without
+    /// attendance caps or channels allow.
+    pub fn reconcile_count(
+        ctx: Context<UpdateEvent>,
+        _event_id: String,
+        actual: u32,
+    ) -> Result<()> {
+        let actual_count = u16::try_from(actual).map_err(|_| ErrorCode::ClaimCountMismatch)?;
+        let event = &mut ctx.accounts.event;
+
+        if !ctx.remaining_accounts.is_empty() {
+            let event_key = event.key();
+            let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+            let mut claims: Vec<AttendeeClaim> = Vec::new();
+            let mut guest_hosts: Vec<Pubkey> = Vec::new();
+
+            for info in ctx.remaining_accounts {
+                if let Some(claim) = AttendeeClaim::try_load(info, ctx.program_id) {
+                    require!(
+                        *info.key
+                            == claim_address(&event.event_id, &claim.attendee, ctx.program_id),
+                        ErrorCode::ClaimCountMismatch
+                    );
+                    claims.push(claim);
+                } else if let Some(guest) = GuestClaim::try_load(info, ctx.program_id) {
+                    require!(guest.event == event_key, ErrorCode::ClaimCountMismatch);
+                    guest_hosts.push(guest.host);
+                } else {
+                    return err!(ErrorCode::ClaimCountMismatch);
+                }
+                require!(!seen.contains(info.key), ErrorCode::ClaimCountMismatch);
+                seen.push(info.key());
+            }
+
+            require!(seen.len() == actual as usize, ErrorCode::ClaimCountMismatch);
+
+            event.in_person_count = 0;
+            event.virtual_count = 0;
+            for channel in event.channels.iter_mut() {
+                channel.used = 0;
+            }
+            for claim in &claims {
+                event.recount(claim);
+            }
+            // Guests sit under their host's attendance type and channel
+            for host in guest_hosts {
+                let claim = claims
+                    .iter()
+                    .find(|claim| claim.attendee == host)
+                    .ok_or(ErrorCode::ClaimCountMismatch)?;
+                event.recount(claim);
+            }
+        } else {
+            require!(
+                event.in_person_cap == 0 && event.virtual_cap == 0 && event.channels.is_empty(),
+                ErrorCode::ClaimAccountsRequired
+            );
+        }
+
+        msg!(
+            "Reconciled claims count for event {}: {} -> {}",
+            event.event_id,
+            event.claims_count,
+            actual_count
+        );
+        event.claims_count = actual_count;
+        Ok(())
+    }
+
+    /// The attendee's badge level with a creator (`BADGE_*`), returned
+    /// through return data. Attendees who never joined have `BADGE_NONE`.
+    pub fn get_badge(ctx: Context<GetBadge>, _creator: Pubkey, _attendee: Pubkey) -> Result<u8> {
+        let profile = AttendeeProfile::try_load(&ctx.accounts.profile, ctx.program_id);
+        Ok(profile.map_or(BADGE_NONE, |profile| profile.badge_level))
+    }
+
+    /// Whether `attendee` has joined the event, returned through return data
+    /// as a `ClaimStatus`. A missing claim is reported, not treated as an error.
+    pub fn get_claim_status(
+        ctx: Context<GetClaimStatus>,
+        _event_id: String,
+        _attendee: Pubkey,
+    ) -> Result<ClaimStatus> {
+        Ok(
+            match AttendeeClaim::try_load(&ctx.accounts.claim, ctx.program_id) {
+                Some(claim) => ClaimStatus {
+                    exists: true,
+                    timestamp: claim.timestamp,
+                },
+                None => ClaimStatus {
+                    exists: false,
+                    timestamp: 0,
+                },
+            },
+        )
+    }
+
+    /// Record the hash of the attendee's off-chain feedback comment, so UIs
+    /// can prove the text they show is what the attendee wrote. One
+    /// submission per claim.
+    pub fn submit_feedback(
+        ctx: Context<SubmitFeedback>,
+        event_id: String,
+        comment_hash: [u8; 32],
+    ) -> Result<()> {
+        let claim = &mut ctx.accounts.claim;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!claim.is_expired(now), ErrorCode::ClaimExpired);
+        require!(
+            !ctx.accounts.event.is_claim_immutable(claim, now),
+            ErrorCode::ClaimImmutable
+        );
+        require!(
+            claim.feedback_hash.is_none(),
+            ErrorCode::AlreadySubmittedFeedback
+        );
+
+        claim.feedback_hash = Some(comment_hash);
+
+        msg!("Feedback submitted for event: {}", event_id);
+        Ok(())
+    }
+
+    /// Issue a short-lived access token for gated content, returned through
+    /// return data and emitted as `AccessTokenIssued`. `nonce` is a fresh
+    /// value the content server handed this session; the token echoes it.
+    /// Transaction logs are public, so the token alone proves nothing: the
+    /// server accepts it only after confirming the transaction succeeded
+    /// against this program ID with the nonce it issued, and must accept
+    /// each nonce once.
+    pub fn generate_access_token(
+        ctx: Context<GetClaimProof>,
+        _event_id: String,
+        nonce: [u8; 32],
+    ) -> Result<AccessToken> {
+        let claim = &ctx.accounts.claim;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!claim.is_expired(now), ErrorCode::ClaimExpired);
+
+        // Never outlive the claim itself
+        let mut expires_at = now.saturating_add(ACCESS_TOKEN_TTL_SECS);
+        if claim.expires_at != 0 {
+            expires_at = expires_at.min(claim.expires_at);
+        }
+
+        let token = AccessToken {
+            event_id: ctx.accounts.event.event_id.clone(),
+            attendee: claim.attendee,
+            expires_at,
+            nonce,
+        };
+        emit!(AccessTokenIssued {
+            event_id: token.event_id.clone(),
+            attendee: token.attendee,
+            expires_at,
+            nonce,
+        });
+        Ok(token)
+    }
+
+    /// Whether `attendee` joined as attendee number `claimed_position`,
+    /// returned through return data. Also re-derives the position
+    /// commitment so any rewritten ordering is caught.
+    pub fn verify_position(
+        ctx: Context<GetClaimStatus>,
+        _event_id: String,
+        attendee: Pubkey,
+        claimed_position: u32,
+    ) -> Result<bool> {
+        let claim = match AttendeeClaim::try_load(&ctx.accounts.claim, ctx.program_id) {
+            Some(claim) => claim,
+            None => return Ok(false),
+        };
+
+        let commitment = position_commitment(
+            &ctx.accounts.event.key(),
+            &attendee,
+            claim.position,
+            claim.slot,
+        );
+        Ok(
+            u32::from(claim.position) == claimed_position
+                && claim.position_commitment == commitment,
+        )
+    }
+
+    /// Close expired claims and return their rent to the attendees. Anyone
+    /// may call this. Remaining accounts are `[claim, attendee wallet,
+    /// profile]` triples in the same order as `attendees`, where `profile`
+    /// is the one the join was credited to (any account for claims that
+    /// never were). Live
+    /// claims are skipped, and so are claims past the event's immutability
+    /// window and claims of hosts whose guests still hold seats. Each swept
+    /// join is taken back off the attendee's profile, so an expired claim
+    /// can't be re-joined to farm badges.
+    pub fn sweep_expired_claims(
+        ctx: Context<SweepExpiredClaims>,
+        _event_id: String,
+        attendees: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(attendees.len() <= MAX_BATCH, ErrorCode::BatchTooLarge);
+        require!(
+            ctx.remaining_accounts.len() == attendees.len() * 3,
+            ErrorCode::BatchAccountsMismatch
+        );
+
+        let event = &mut ctx.accounts.event;
+        let now = Clock::get()?.unix_timestamp;
+        let mut swept = 0u32;
+
+        for (attendee, accounts) in attendees.iter().zip(ctx.remaining_accounts.chunks(3)) {
+            let (claim_info, wallet, profile_info) = (&accounts[0], &accounts[1], &accounts[2]);
+            require_keys_eq!(wallet.key(), *attendee, ErrorCode::BatchAccountsMismatch);
+
+            let claim = AttendeeClaim::try_load(claim_info, ctx.program_id)
+                .ok_or(ErrorCode::BatchAccountsMismatch)?;
+            require!(
+                claim.attendee == *attendee
+                    && claim_info.key() == claim_address(&event.event_id, attendee, ctx.program_id),
+                ErrorCode::BatchAccountsMismatch
+            );
+            if !claim.is_expired(now)
+                || event.is_claim_immutable(&claim, now)
+                || claim.guests_registered > 0
+            {
+                continue;
+            }
+
+            // The creator may have changed since the join, so take it off the
+            // profile it was credited to. Imported claims never were.
+            if let Some(creator) = claim.credited_creator {
+                require_keys_eq!(
+                    profile_info.key(),
+                    profile_address(&creator, attendee, ctx.program_id),
+                    ErrorCode::BatchAccountsMismatch
+                );
+                if let Some(mut profile) = AttendeeProfile::try_load(profile_info, ctx.program_id) {
+                    profile.record_leave();
+                    profile.try_serialize(&mut &mut profile_info.try_borrow_mut_data()?[..])?;
+                }
+            }
+
+            close_account(claim_info, wallet)?;
+            event.release(&claim);
+            swept += 1;
+        }
+
+        msg!(
+            "Swept {} expired claims from event {}",
+            swept,
+            event.event_id
+        );
+        Ok(())
+    }
+
+    /// Pre-check whether `attendee` could join right now, returned through
+    /// return data. `reason_code` is the error code `join_event` would fail
+    /// with (0 when joinable). Checks that depend on what the attendee
+    /// submits at join time (ticket number, agenda hash, attestation and age
+    /// co-signatures, attendance type, channel) are not evaluated, except
+    /// that an event whose channels are all full can't be joined.
+    pub fn can_join(
+        ctx: Context<CanJoin>,
+        _event_id: String,
+        attendee: Pubkey,
+    ) -> Result<JoinEligibility> {
+        let event = &ctx.accounts.event;
+        let blocked = |code: ErrorCode| JoinEligibility {
+            can_join: false,
+            reason_code: u32::from(code) as u16,
+        };
+
+        if event.archived {
+            return Ok(blocked(ErrorCode::EventArchived));
+        }
+        if event.compressed_claims {
+            return Ok(blocked(ErrorCode::CompressedClaimsOnly));
+        }
+        // Claims still in the legacy layout count as joined too
+        if !ctx.accounts.claim.data_is_empty() {
+            return Ok(blocked(ErrorCode::AlreadyJoined));
+        }
+        if event.has_blocklist {
+            // Without the blocklist account the join would be refused too
+            let banned = match &ctx.accounts.blocklist {
+                Some(blocklist) => blocklist.banned.contains(&attendee),
+                None => true,
+            };
+            if banned {
+                return Ok(blocked(ErrorCode::AttendeeBlocked));
+            }
+        }
+        if event.requires_parent_claim {
+            let now = Clock::get()?.unix_timestamp;
+            let has_parent_claim = match (&ctx.accounts.parent_event, &ctx.accounts.parent_claim) {
+                (Some(parent_event), Some(parent_claim)) => {
+                    event.parent_event == Some(parent_event.key())
+                        && parent_claim.key()
+                            == claim_address(&parent_event.event_id, &attendee, ctx.program_id)
+                        && parent_claim.attendee == attendee
+                        && !parent_claim.is_expired(now)
+                }
+                _ => false,
+            };
+            if !has_parent_claim {
+                return Ok(blocked(ErrorCode::ParentClaimRequired));
+            }
+        }
+        if !event.unlimited && event.claims_count >= event.capacity() {
+            return Ok(blocked(ErrorCode::MaxClaimsReached));
+        }
+        if !event.channels.is_empty()
+            && event
+                .channels
+                .iter()
+                .all(|channel| channel.used >= channel.cap)
+        {
+            return Ok(blocked(ErrorCode::ChannelFull));
+        }
+
+        Ok(JoinEligibility {
+            can_join: true,
+            reason_code: 0,
+        })
+    }
+
+    /// Export the attendee's claim as a Borsh-encoded `ClaimProof` through
+    /// return data, for the organizer to sign and present off-chain
+    pub fn get_claim_proof(ctx: Context<GetClaimProof>, _event_id: String) -> Result<ClaimProof> {
+        let event = &ctx.accounts.event;
+        let claim = &ctx.accounts.claim;
+        require!(
+            !claim.is_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::ClaimExpired
+        );
+
+        Ok(ClaimProof {
+            event_id: event.event_id.clone(),
+            attendee: claim.attendee,
+            timestamp: claim.timestamp,
+            creator: event.creator,
+        })
+    }
+}
+
+/// Event IDs are used verbatim as PDA seeds, so reject ones that would be
+/// ambiguous to type or display. IDs are case-sensitive: "Demo" and "demo"
+/// are different events, and clients should normalize before deriving.
+fn validate_event_id(event_id: &str) -> Result<()> {
+    require!(event_id.len() <= 16, ErrorCode::EventIdTooLong);
+    require!(
+        !event_id.is_empty()
+            && !event_id
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control()),
+        ErrorCode::InvalidEventId
+    );
+    Ok(())
+}
+
+/// Validate and fill in a newly created event, shared by every instruction
+/// that creates one. Everything not passed in starts at its default;
+/// callers set their own extras afterwards and refresh the mode flags if
+/// those extras affect them.
+#[allow(clippy::too_many_arguments)]
+fn init_event(
+    event: &mut Event,
+    creator: Pubkey,
+    event_id: String,
+    name: String,
+    description: String,
+    venue: String,
+    date: String,
+    max_claims: u16,
+    full_redirect_uri: Option<String>,
+    claim_ttl_secs: i64,
+    is_virtual: bool,
+    stream_uri: String,
+    unlimited: bool,
+) -> Result<()> {
+    // Validate inputs
+    validate_event_id(&event_id)?;
+    require!(name.len() <= 50, ErrorCode::NameTooLong);
+    require!(description.len() <= 200, ErrorCode::DescriptionTooLong);
+    require!(venue.len() <= 100, ErrorCode::VenueTooLong);
+    require!(date.len() <= 30, ErrorCode::DateTooLong);
+    // A zero cap only makes sense when the cap is ignored
+    require!(max_claims > 0 || unlimited, ErrorCode::InvalidMaxClaims);
+    if let Some(uri) = &full_redirect_uri {
+        require!(uri.len() <= 100, ErrorCode::RedirectUriTooLong);
+    }
+    require!(claim_ttl_secs >= 0, ErrorCode::InvalidClaimTtl);
+    require!(stream_uri.len() <= 100, ErrorCode::StreamUriTooLong);
+    if is_virtual {
+        require!(!stream_uri.is_empty(), ErrorCode::StreamUriRequired);
+    } else {
+        require!(!venue.is_empty(), ErrorCode::VenueRequired);
+    }
+
+    event.creator = creator;
+    event.event_id = event_id;
+    event.name = name;
+    event.description = description;
+    event.venue = venue;
+    event.date = date;
+    event.max_claims = max_claims;
+    event.claims_count = 0;
+    event.organizer_memo = String::new();
+    event.full_redirect_uri = full_redirect_uri;
+    event.has_allowlist = false;
+    event.archived = false;
+    event.claim_ttl_secs = claim_ttl_secs;
+    event.parent_event = None;
+    event.requires_parent_claim = false;
+    event.is_virtual = is_virtual;
+    event.stream_uri = stream_uri;
+    event.creator_pays_rent = false;
+    event.next_serial = 1;
+    event.agenda_hash = None;
+    event.metadata_locked = false;
+    event.has_blocklist = false;
+    event.attestation_authority = None;
+    event.compressed_claims = false;
+    event.claims_root = [0; 32];
+    event.unlimited = unlimited;
+    event.pending_owner = None;
+    event.in_person_cap = 0;
+    event.virtual_cap = 0;
+    event.in_person_count = 0;
+    event.virtual_count = 0;
+    event.guest_allowance = 0;
+    event.channels = Vec::new();
+    event.overbook_bps = 0;
+    event.display_currency = String::new();
+    event.display_price = 0;
+    event.min_age = 0;
+    event.age_verifier = None;
+    event.immutable_after_secs = 0;
+    event.milestone_reached = 0;
+    event.refresh_mode_flags();
+    Ok(())
+}
+
+/// Fill in a newly created claim for an admitted attendee and take its
+/// serial, shared by `join_event` and `create_and_join`
+fn issue_claim(
+    event: &mut Account<Event>,
+    claim: &mut AttendeeClaim,
+    attendee: Pubkey,
+    attendance_type: u8,
+    channel: Option<u8>,
+) -> Result<()> {
+    claim.attendee = attendee;
+    // Only claims that expire or become immutable depend on the time.
+    // Without either, a failed sysvar read records a zero timestamp and slot
+    // instead of refusing the join.
+    let clock = match Clock::get() {
+        Ok(clock) => clock,
+        Err(_) if event.claim_ttl_secs == 0 && event.immutable_after_secs == 0 => Clock::default(),
+        Err(err) => return Err(err.into()),
+    };
+    claim.timestamp = clock.unix_timestamp;
+    claim.slot = clock.slot;
+    claim.serial = event.next_serial;
+    claim.acknowledged = event.agenda_hash.is_some();
+    claim.feedback_hash = None;
+    claim.position = event.claims_count;
+    claim.attendance_type = attendance_type;
+    claim.guests_registered = 0;
+    claim.channel = channel;
+    claim.imported = false;
+    claim.credited_creator = None;
+    claim.position_commitment =
+        position_commitment(&event.key(), &claim.attendee, claim.position, claim.slot);
+    claim.expires_at = if event.claim_ttl_secs == 0 {
+        0
+    } else {
+        clock
+            .unix_timestamp
+            .checked_add(event.claim_ttl_secs)
+            .ok_or(ErrorCode::InvalidClaimTtl)?
+    };
+
+    // Serials only move forward, so numbers freed by claims that go away
+    // are never handed out again
+    event.next_serial = event
+        .next_serial
+        .checked_add(1)
+        .ok_or(ErrorCode::SerialOverflow)?;
+    Ok(())
+}
+
+/// Binds an attendee's position to the event and the slot they joined in
+fn position_commitment(event: &Pubkey, attendee: &Pubkey, position: u16, slot: u64) -> [u8; 32] {
+    hashv(&[
+        event.as_ref(),
+        attendee.as_ref(),
+        position.to_le_bytes().as_ref(),
+        slot.to_le_bytes().as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// Address of an attendee's claim PDA. Claims don't store their event, so
+/// this is what ties a claim passed outside of seed constraints to one.
+fn claim_address(event_id: &str, attendee: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"claim", event_id.as_bytes(), attendee.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+/// Address of an attendee's profile PDA with `creator`
+fn profile_address(creator: &Pubkey, attendee: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"profile", creator.as_ref(), attendee.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+/// Create a program-owned PDA the way Anchor's `init` does: `create_account`
+/// when the address holds nothing, otherwise top up, allocate and assign, so
+/// sending lamports to the address first can't block it
+fn create_pda<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    use anchor_lang::system_program::{
+        allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+    };
+
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[seeds];
+    let lamports = target.lamports();
+    if lamports == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                signer,
+            ),
+            rent,
+            space as u64,
+            owner,
+        );
+    }
+
+    let top_up = rent.saturating_sub(lamports);
+    if top_up > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: target.clone(),
+            },
+            signer,
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: target.clone(),
+            },
+            signer,
+        ),
+        owner,
+    )
+}
+
+/// Close a program-owned account, moving its rent to `destination`
+fn close_account(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = info.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **info.try_borrow_mut_lamports()? = 0;
+
+    info.assign(&anchor_lang::system_program::ID);
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+/// Leaf committed to the claim tree for one compressed claim
+fn compressed_claim_leaf(attendee: &Pubkey, event_id: &str, timestamp: i64) -> [u8; 32] {
+    hashv(&[
+        attendee.as_ref(),
+        event_id.as_bytes(),
+        timestamp.to_le_bytes().as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// Fold a leaf and its sibling path (bottom-up) into a Merkle root
+fn merkle_root_from_proof(leaf: [u8; 32], index: u32, proof: &[[u8; 32]]) -> [u8; 32] {
+    let mut node = leaf;
+    let mut index = index;
+    for sibling in proof {
+        node = if index % 2 == 0 {
+            hashv(&[node.as_ref(), sibling.as_ref()]).to_bytes()
+        } else {
+            hashv(&[sibling.as_ref(), node.as_ref()]).to_bytes()
+        };
+        index /= 2;
+    }
+    node
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct CreateEvent<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Event::space(&event_id),
+        seeds = [b"event", event_id.as_bytes()],
+        bump
+    )]
+    pub event: Account<'info, Event>,
+    
+    /// Stats for the current UTC day, created by the first event of the day
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + DailyStats::SPACE,
+        seeds = [b"daily", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct UpdateEvent<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct UpdateEventDetails<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// Audit trail of detail changes, created by the first update
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + EventHistory::SPACE,
+        seeds = [b"history", event_id.as_bytes()],
+        bump
+    )]
+    pub history: Account<'info, EventHistory>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct JoinEvent<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AttendeeClaim::SPACE,
+        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
+        bump,
+        constraint = event.event_id == event_id @ ErrorCode::EventIdMismatch,
+    )]
+    pub claim: Account<'info, AttendeeClaim>,
+
+    /// Required when the event has an allowlist
+    #[account(
+        mut,
+        seeds = [b"allowlist", event_id.as_bytes()],
+        bump,
+    )]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// Required when the event has a blocklist
+    #[account(
+        seeds = [b"blocklist", event_id.as_bytes()],
+        bump,
+    )]
+    pub blocklist: Option<Account<'info, Blocklist>>,
+
+    /// Required when the event has an attestation authority
+    pub attestation_authority: Option<Signer<'info>>,
+
+    /// Required when the event is age-restricted
+    pub age_verifier: Option<Signer<'info>>,
+
+    /// Required when the event is a track that requires a parent claim
+    pub parent_event: Option<Account<'info, Event>>,
+
+    /// The attendee's claim on `parent_event`
+    pub parent_claim: Option<Account<'info, AttendeeClaim>>,
+
+    /// The attendee's standing with the creator, created on their first join
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AttendeeProfile::SPACE,
+        seeds = [b"profile", event.creator.as_ref(), attendee.key().as_ref()],
+        bump,
+    )]
+    pub profile: Account<'info, AttendeeProfile>,
+    
+    pub attendee: Signer<'info>,
+
+    /// Pays the claim rent: the attendee, or the creator on sponsored events
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct VerifyAttendance<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// CHECK: may not be initialized yet; owner and layout are checked in the handler
+    #[account(
+        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
+        bump,
+    )]
+    pub claim: UncheckedAccount<'info>,
+
+    /// CHECK: only used to derive the claim address
+    pub attendee: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String, allowlist_bitmap: Vec<u8>)]
+pub struct CreateAllowlist<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Allowlist::space(allowlist_bitmap.len()),
+        seeds = [b"allowlist", event_id.as_bytes()],
+        bump
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(parent_event_id: String, track_id: String)]
+pub struct CreateTrack<'info> {
+    #[account(
+        seeds = [b"event", parent_event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub parent_event: Account<'info, Event>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Event::space(&track_id),
+        seeds = [b"event", track_id.as_bytes()],
+        bump
+    )]
+    pub track: Account<'info, Event>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + DailyStats::SPACE,
+        seeds = [b"daily", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct ManageBlocklist<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + Blocklist::SPACE,
+        seeds = [b"blocklist", event_id.as_bytes()],
+        bump
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String, depth: u8)]
+pub struct EnableCompressedClaims<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + ClaimTree::space(depth),
+        seeds = [b"claim_tree", event_id.as_bytes()],
+        bump
+    )]
+    pub claim_tree: Account<'info, ClaimTree>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct AppendCompressedClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        mut,
+        seeds = [b"claim_tree", event_id.as_bytes()],
+        bump,
+    )]
+    pub claim_tree: Account<'info, ClaimTree>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct VerifyCompressedClaim<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        seeds = [b"claim_tree", event_id.as_bytes()],
+        bump,
+    )]
+    pub claim_tree: Account<'info, ClaimTree>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_event_id: String, new_event_id: String)]
+pub struct CloneEvent<'info> {
+    #[account(
+        seeds = [b"event", source_event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub source_event: Account<'info, Event>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Event::space(&new_event_id),
+        seeds = [b"event", new_event_id.as_bytes()],
+        bump
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + DailyStats::SPACE,
+        seeds = [b"daily", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: String)]
+pub struct CreateTemplate<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + EventTemplate::SPACE,
+        seeds = [b"template", creator.key().as_ref(), template_id.as_bytes()],
+        bump
+    )]
+    pub template: Account<'info, EventTemplate>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: String)]
+pub struct UpdateTemplate<'info> {
+    #[account(
+        mut,
+        seeds = [b"template", creator.key().as_ref(), template_id.as_bytes()],
+        bump,
+    )]
+    pub template: Account<'info, EventTemplate>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: String, event_id: String)]
+pub struct CreateEventFromTemplate<'info> {
+    /// CHECK: may not exist; loaded in the handler so a missing template
+    /// reports `TemplateNotFound`
+    #[account(
+        seeds = [b"template", creator.key().as_ref(), template_id.as_bytes()],
+        bump,
+    )]
+    pub template: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Event::space(&event_id),
+        seeds = [b"event", event_id.as_bytes()],
+        bump
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + DailyStats::SPACE,
+        seeds = [b"daily", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct AcceptOwnership<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetProgramInfo {}
+
+#[derive(Accounts)]
+pub struct GetCreatorTotals {}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct CreateAndJoin<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Event::space(&event_id),
+        seeds = [b"event", event_id.as_bytes()],
+        bump
+    )]
+    pub event: Account<'info, Event>,
+
+    /// Stats for the current UTC day, created by the first event of the day
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + DailyStats::SPACE,
+        seeds = [b"daily", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + AttendeeClaim::SPACE,
+        seeds = [b"claim", event_id.as_bytes(), creator.key().as_ref()],
+        bump,
+    )]
+    pub claim: Account<'info, AttendeeClaim>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + AttendeeProfile::SPACE,
+        seeds = [b"profile", creator.key().as_ref(), creator.key().as_ref()],
+        bump,
+    )]
+    pub profile: Account<'info, AttendeeProfile>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct ImportClaims<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// Pays the rent of the imported claims
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct MigrateClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// CHECK: still in the legacy `Claim` layout; owner and layout are
+    /// checked in the handler
+    #[account(
+        mut,
+        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
+        bump,
+    )]
+    pub claim: UncheckedAccount<'info>,
+
+    /// CHECK: only derives the claim address and receives any freed rent
+    #[account(mut)]
+    pub attendee: UncheckedAccount<'info>,
+
+    /// Covers the extra rent of the larger layout
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct GetEvent<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct EventExists<'info> {
+    /// CHECK: may not be initialized; owner and discriminator are checked in the handler
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String, attendee: Pubkey)]
+pub struct GetClaimStatus<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// CHECK: may not be initialized; owner and layout are checked in the handler
+    #[account(
+        seeds = [b"claim", event_id.as_bytes(), attendee.as_ref()],
+        bump,
+    )]
+    pub claim: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey, attendee: Pubkey)]
+pub struct GetBadge<'info> {
+    /// CHECK: may not be initialized; owner and layout are checked in the handler
+    #[account(
+        seeds = [b"profile", creator.as_ref(), attendee.as_ref()],
+        bump,
+    )]
+    pub profile: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct SubmitFeedback<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        mut,
+        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
+        bump,
+    )]
+    pub claim: Account<'info, AttendeeClaim>,
+
+    pub attendee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct RegisterGuest<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        mut,
+        seeds = [b"claim", event_id.as_bytes(), host.key().as_ref()],
+        bump,
+    )]
+    pub host_claim: Account<'info, AttendeeClaim>,
+
+    #[account(
+        init,
+        payer = host,
+        space = 8 + GuestClaim::SPACE,
+        seeds = [
+            b"guest",
+            event_id.as_bytes(),
+            host.key().as_ref(),
+            event.next_serial.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub guest_claim: Account<'info, GuestClaim>,
+
+    #[account(mut)]
+    pub host: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct SweepExpiredClaims<'info> {
+    #[account(
+        mut,
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String, attendee: Pubkey)]
+pub struct CanJoin<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    /// CHECK: usually not initialized; owner and layout are checked in the handler
+    #[account(
+        seeds = [b"claim", event_id.as_bytes(), attendee.as_ref()],
+        bump,
+    )]
+    pub claim: UncheckedAccount<'info>,
+
+    /// Same optional accounts as `join_event`
+    #[account(
+        seeds = [b"blocklist", event_id.as_bytes()],
+        bump,
+    )]
+    pub blocklist: Option<Account<'info, Blocklist>>,
+
+    pub parent_event: Option<Account<'info, Event>>,
+
+    pub parent_claim: Option<Account<'info, AttendeeClaim>>,
+}
+
+#[derive(Accounts)]
+#[instruction(event_id: String)]
+pub struct GetClaimProof<'info> {
+    #[account(
+        seeds = [b"event", event_id.as_bytes()],
+        bump,
+    )]
+    pub event: Account<'info, Event>,
+
+    #[account(
+        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
+        bump,
+    )]
+    pub claim: Account<'info, AttendeeClaim>,
+
+    pub attendee: Signer<'info>,
+}
+
+#[account]
+#[cfg_attr(test, derive(Default))]
+pub struct Event {
+    pub creator: Pubkey,
+    pub event_id: String,
+    pub name: String,
+    pub description: String,
+    pub venue: String,
+    pub date: String,
+    pub max_claims: u16,
+    pub claims_count: u16,
+    /// Private notes for the organizer. This is ordinary account data and
+    /// anyone can read it on-chain; it is only "private" in that front-ends
+    /// don't show it to attendees.
+    pub organizer_memo: String,
+    pub full_redirect_uri: Option<String>,
+    pub has_allowlist: bool,
+    pub archived: bool,
+    /// How long claims stay valid after joining, in seconds; 0 = forever
+    pub claim_ttl_secs: i64,
+    /// Set on tracks: the event this track belongs to
+    pub parent_event: Option<Pubkey>,
+    pub requires_parent_claim: bool,
+    /// Virtual events need a `stream_uri`; in-person events need a `venue`
+    pub is_virtual: bool,
+    pub stream_uri: String,
+    pub creator_pays_rent: bool,
+    /// Serial number the next claim receives; starts at 1
+    pub next_serial: u32,
+    /// Hash of the agenda attendees must acknowledge before joining
+    pub agenda_hash: Option<[u8; 32]>,
+    pub metadata_locked: bool,
+    pub has_blocklist: bool,
+    /// When set, this key must co-sign every join (one device, one claim)
+    pub attestation_authority: Option<Pubkey>,
+    /// Claims are leaves of the event's `ClaimTree` rather than accounts
+    pub compressed_claims: bool,
+    /// Current root of the claim tree
+    pub claims_root: [u8; 32],
+    /// Ignore `max_claims` when admitting attendees
+    pub unlimited: bool,
+    /// Proposed new creator, waiting for them to accept
+    pub pending_owner: Option<Pubkey>,
+    /// Per-type capacity for hybrid events; 0 = no separate cap
+    pub in_person_cap: u16,
+    pub virtual_cap: u16,
+    pub in_person_count: u16,
+    pub virtual_count: u16,
+    /// Guests each attendee may register through `register_guest`
+    pub guest_allowance: u8,
+    /// Per-channel caps, on top of the global `max_claims`
+    pub channels: Vec<Channel>,
+    /// Registrations accepted past `max_claims`, in basis points of it
+    pub overbook_bps: u16,
+    /// Currency code front-ends show prices in, e.g. "USD"; display only
+    pub display_currency: String,
+    /// Price in minor units of `display_currency`; display only
+    pub display_price: u64,
+    /// Minimum attendee age; 0 = no restriction
+    pub min_age: u8,
+    /// Authority whose co-signature attests an attendee meets `min_age`
+    pub age_verifier: Option<Pubkey>,
+    /// Claim age after which it can't be changed or closed; 0 = never
+    pub immutable_after_secs: i64,
+    /// `MODE_*` bits derived from the fields above, see `refresh_mode_flags`
+    pub mode_flags: u32,
+    /// Highest entry of `MILESTONES` announced so far; 0 = none
+    pub milestone_reached: u16,
+}
+
+impl Event {
+    fn space(event_id: &str) -> usize {
+        // 32 (pubkey) + sizes of strings + 2 + 2 (u16) + padding
+        32 + 
+        4 + event_id.len() + 
+        4 + 50 +  // name: max 50 chars
+        4 + 200 + // description: max 200 chars
+        4 + 100 + // venue: max 100 chars
+        4 + 30 +  // date: max 30 chars
+        2 + 2 +   // max_claims and claims_count
+        4 + 100 + // organizer_memo: max 100 chars
+        1 + 4 + 100 + // full_redirect_uri: optional, max 100 chars
+        1 +       // has_allowlist
+        1 +       // archived
+        8 +       // claim_ttl_secs
+        1 + 32 +  // parent_event
+        1 +       // requires_parent_claim
+        1 +       // is_virtual
+        4 + 100 + // stream_uri: max 100 chars
+        1 +       // creator_pays_rent
+        4 +       // next_serial
+        1 + 32 +  // agenda_hash
+        1 +       // metadata_locked
+        1 +       // has_blocklist
+        1 + 32 +  // attestation_authority
+        1 + 32 +  // compressed_claims and claims_root
+        1 +       // unlimited
+        1 + 32 +  // pending_owner
+        2 + 2 +   // in_person_cap and virtual_cap
+        2 + 2 +   // in_person_count and virtual_count
+        1 +       // guest_allowance
+        4 + Channel::SPACE * Channel::MAX_CHANNELS + // channels
+        2 +       // overbook_bps
+        4 + 8 +   // display_currency: max 8 chars
+        8 +       // display_price
+        1 +       // min_age
+        1 + 32 +  // age_verifier
+        8 +       // immutable_after_secs
+        4 +       // mode_flags
+        2 +       // milestone_reached
+        100       // some padding
+    }
+
+    /// Take one seat, failing if the event is full. This is the only capacity
+    /// check, made against the same counter it writes back.
+    fn admit(&mut self) -> Result<()> {
+        let new_count = self
+            .claims_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MaxClaimsReached)?;
+        if !self.unlimited && new_count > self.capacity() {
+            // The transaction fails, but its logs still carry the event so
+            // clients can send the user to the overflow page
+            emit!(EventFull {
+                event_id: self.event_id.clone(),
+                redirect_uri: self.full_redirect_uri.clone(),
+            });
+            return err!(ErrorCode::MaxClaimsReached);
+        }
+
+        self.claims_count = new_count;
+
+        // Counts drop when claims are swept or reconciled, so only announce
+        // each milestone the first time it's crossed
+        if MILESTONES.contains(&new_count) && new_count > self.milestone_reached {
+            emit!(MilestoneReached {
+                event_id: self.event_id.clone(),
+                milestone: u32::from(new_count),
+            });
+            self.milestone_reached = new_count;
+        }
+        Ok(())
+    }
+
+    /// Count a seat against the sub-cap for the attendee's chosen type
+    fn admit_attendance_type(&mut self, attendance_type: u8) -> Result<()> {
+        let (count, cap, full) = match attendance_type {
+            ATTENDANCE_IN_PERSON => (
+                &mut self.in_person_count,
+                self.in_person_cap,
+                ErrorCode::InPersonCapReached,
+            ),
+            ATTENDANCE_VIRTUAL => (
+                &mut self.virtual_count,
+                self.virtual_cap,
+                ErrorCode::VirtualCapReached,
+            ),
+            _ => return err!(ErrorCode::InvalidAttendanceType),
+        };
+
+        let new_count = count.checked_add(1).ok_or(full)?;
+        require!(cap == 0 || new_count <= cap, full);
+        *count = new_count;
+        Ok(())
+    }
+
+    /// Count a seat that is already taken against its attendance type and
+    /// channel, without enforcing caps. Migration and reconciliation use
+    /// it to rebuild counters; it undoes `release` except for `claims_count`.
+    fn recount(&mut self, claim: &AttendeeClaim) {
+        match claim.attendance_type {
+            ATTENDANCE_IN_PERSON => self.in_person_count = self.in_person_count.saturating_add(1),
+            ATTENDANCE_VIRTUAL => self.virtual_count = self.virtual_count.saturating_add(1),
+            _ => {}
+        }
+        if let Some(channel) = claim
+            .channel
+            .and_then(|index| self.channels.get_mut(index as usize))
+        {
+            channel.used = channel.used.saturating_add(1);
+        }
+    }
+
+    /// Count a seat against the registration channel it came through.
+    /// Events without channels take joins with no channel; events with
+    /// channels require a known one. Returns the channel's index.
+    fn admit_channel(&mut self, channel_id: Option<&str>) -> Result<Option<u8>> {
+        if self.channels.is_empty() {
+            require!(channel_id.is_none(), ErrorCode::InvalidChannel);
+            return Ok(None);
+        }
+
+        let channel_id = channel_id.ok_or(ErrorCode::InvalidChannel)?;
+        let index = self
+            .channels
+            .iter()
+            .position(|channel| channel.id == channel_id)
+            .ok_or(ErrorCode::InvalidChannel)?;
+
+        self.take_channel_seat(index as u8)?;
+        Ok(Some(index as u8))
+    }
+
+    /// Count a seat against the channel at `index`
+    fn take_channel_seat(&mut self, index: u8) -> Result<()> {
+        let channel = self
+            .channels
+            .get_mut(index as usize)
+            .ok_or(ErrorCode::InvalidChannel)?;
+        require!(channel.used < channel.cap, ErrorCode::ChannelFull);
+        channel.used += 1;
+        Ok(())
+    }
+
+    /// Give back the seat of a claim that no longer exists
+    fn release(&mut self, claim: &AttendeeClaim) {
+        self.claims_count = self.claims_count.saturating_sub(1);
+        match claim.attendance_type {
+            ATTENDANCE_IN_PERSON => self.in_person_count = self.in_person_count.saturating_sub(1),
+            ATTENDANCE_VIRTUAL => self.virtual_count = self.virtual_count.saturating_sub(1),
+            _ => {}
+        }
+        if let Some(channel) = claim
+            .channel
+            .and_then(|index| self.channels.get_mut(index as usize))
+        {
+            channel.used = channel.used.saturating_sub(1);
+        }
+    }
+
+    /// Recompute `mode_flags`. Every instruction that changes one of the
+    /// fields below calls this before returning.
+    fn refresh_mode_flags(&mut self) {
+        let modes = [
+            (self.is_virtual, MODE_VIRTUAL),
+            (self.has_allowlist, MODE_ALLOWLIST),
+            (self.has_blocklist, MODE_BLOCKLIST),
+            (self.parent_event.is_some(), MODE_TRACK),
+            (self.attestation_authority.is_some(), MODE_ATTESTATION),
+            (self.compressed_claims, MODE_COMPRESSED_CLAIMS),
+            (self.claim_ttl_secs != 0, MODE_CLAIM_EXPIRY),
+            (
+                self.in_person_cap != 0 || self.virtual_cap != 0,
+                MODE_HYBRID_CAPS,
+            ),
+            (self.guest_allowance != 0, MODE_GUESTS),
+            (self.creator_pays_rent, MODE_SPONSORED_RENT),
+            (self.unlimited, MODE_UNLIMITED),
+            (!self.channels.is_empty(), MODE_CHANNELS),
+            (self.overbook_bps != 0, MODE_OVERBOOKING),
+            (self.min_age != 0, MODE_AGE_RESTRICTED),
+            (self.agenda_hash.is_some(), MODE_AGENDA),
+            (self.immutable_after_secs != 0, MODE_IMMUTABLE_CLAIMS),
+        ];
+        self.mode_flags = modes
+            .iter()
+            .filter(|(on, _)| *on)
+            .fold(0, |flags, (_, bit)| flags | bit);
+    }
+
+    /// Whether `claim` is past the event's immutability window
+    fn is_claim_immutable(&self, claim: &AttendeeClaim, now: i64) -> bool {
+        self.immutable_after_secs != 0
+            && now.saturating_sub(claim.timestamp) >= self.immutable_after_secs
+    }
+
+    /// Registrations accepted: `max_claims` plus the overbooking allowance
+    fn capacity(&self) -> u16 {
+        let extra = u32::from(self.max_claims) * u32::from(self.overbook_bps) / 10_000;
+        u16::try_from(u32::from(self.max_claims) + extra).unwrap_or(u16::MAX)
+    }
+
+    /// Claims still available. Never underflows, even if `claims_count`
+    /// somehow ended up above the capacity. Unlimited events are bounded only
+    /// by the width of `claims_count`.
+    pub fn claims_remaining(&self) -> u32 {
+        let cap = if self.unlimited {
+            u16::MAX
+        } else {
+            self.capacity()
+        };
+        u32::from(cap).saturating_sub(u32::from(self.claims_count))
+    }
+
+    fn try_load(info: &AccountInfo, program_id: &Pubkey) -> Option<Event> {
+        if info.owner != program_id || info.data_is_empty() {
+            return None;
+        }
+        let data = info.try_borrow_data().ok()?;
+        Event::try_deserialize(&mut &data[..]).ok()
+    }
+}
+
+#[account]
+pub struct Allowlist {
+    pub event: Pubkey,
+    /// Bit set = ticket number was issued
+    pub allowlist_bitmap: Vec<u8>,
+    /// Bit set = ticket number has been used to join
+    pub consumed_bitmap: Vec<u8>,
+}
+
+impl Allowlist {
+    /// Keeps `create_allowlist` within a single transaction (4096 tickets)
+    const MAX_BYTES: usize = 512;
+
+    fn space(bitmap_len: usize) -> usize {
+        // 32 (pubkey) + two bitmaps of the same length
+        32 + 4 + bitmap_len + 4 + bitmap_len
+    }
+
+    /// Mark a ticket as used, failing if it was never issued or is already spent
+    fn consume(&mut self, ticket_number: u32) -> Result<()> {
+        let byte = (ticket_number / 8) as usize;
+        let mask = 1u8 << (ticket_number % 8);
+
+        let issued = self
+            .allowlist_bitmap
+            .get(byte)
+            .is_some_and(|bits| bits & mask != 0);
+        require!(issued, ErrorCode::TicketNotAllowed);
+        require!(
+            self.consumed_bitmap[byte] & mask == 0,
+            ErrorCode::TicketAlreadyUsed
+        );
+
+        self.consumed_bitmap[byte] |= mask;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Blocklist {
+    pub event: Pubkey,
+    pub banned: Vec<Pubkey>,
+}
+
+impl Blocklist {
+    const MAX_ENTRIES: usize = 50;
+    // 32 (pubkey) + vec of up to MAX_ENTRIES pubkeys
+    const SPACE: usize = 32 + 4 + 32 * Self::MAX_ENTRIES;
+}
+
+/// Append-only Merkle tree of compressed claims. Keeps only the rightmost
+/// filled node of each level, enough to compute the next root.
+#[account]
+pub struct ClaimTree {
+    pub event: Pubkey,
+    pub depth: u8,
+    pub next_index: u32,
+    pub filled_subtrees: Vec<[u8; 32]>,
+}
+
+impl ClaimTree {
+    /// 2^20 leaves, well past what `claims_count` can address
+    const MAX_DEPTH: u8 = 20;
+
+    fn space(depth: u8) -> usize {
+        // 32 (pubkey) + 1 (u8) + 4 (u32) + one node per level
+        32 + 1 + 4 + 4 + 32 * depth as usize
+    }
+
+    fn empty_root(depth: u8) -> [u8; 32] {
+        let mut zero = [0; 32];
+        for _ in 0..depth {
+            zero = hashv(&[zero.as_ref(), zero.as_ref()]).to_bytes();
+        }
+        zero
+    }
+
+    /// Insert the next leaf and return the new root
+    fn append(&mut self, leaf: [u8; 32]) -> Result<[u8; 32]> {
+        require!(
+            u64::from(self.next_index) < 1u64 << self.depth,
+            ErrorCode::ClaimTreeFull
+        );
+
+        let mut node = leaf;
+        let mut index = self.next_index;
+        let mut zero = [0; 32];
+        for filled in self.filled_subtrees.iter_mut() {
+            node = if index % 2 == 0 {
+                *filled = node;
+                hashv(&[node.as_ref(), zero.as_ref()]).to_bytes()
+            } else {
+                hashv(&[filled.as_ref(), node.as_ref()]).to_bytes()
+            };
+            zero = hashv(&[zero.as_ref(), zero.as_ref()]).to_bytes();
+            index /= 2;
+        }
+
+        self.next_index += 1;
+        Ok(node)
+    }
+}
+
+/// Publicly visible event details tracked in `EventHistory`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EventField {
+    Name,
+    Description,
+    Venue,
+    Date,
+}
 
-        // Validate inputs
-        require!(event_id.len() <= 16, ErrorCode::EventIdTooLong);
-        require!(name.len() <= 50, ErrorCode::NameTooLong);
-        require!(description.len() <= 200, ErrorCode::DescriptionTooLong);
-        require!(venue.len() <= 100, ErrorCode::VenueTooLong);
-        require!(date.len() <= 30, ErrorCode::DateTooLong);
-        require!(max_claims > 0, ErrorCode::InvalidMaxClaims);
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct HistoryEntry {
+    pub field: EventField,
+    pub timestamp: i64,
+    pub editor: Pubkey,
+}
 
-        event.creator = creator.key();
-        event.event_id = event_id;
-        event.name = name;
-        event.description = description;
-        event.venue = venue;
-        event.date = date;
-        event.max_claims = max_claims;
-        event.claims_count = 0;
+impl HistoryEntry {
+    // 1 (enum tag) + 8 (i64) + 32 (pubkey)
+    const SPACE: usize = 1 + 8 + 32;
+}
 
-        msg!("Created event: {}", event.event_id);
+/// Append-only log of changes `update_event` made to an event's public
+/// details, oldest first. The organizer memo is not tracked.
+#[account]
+pub struct EventHistory {
+    pub event: Pubkey,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl EventHistory {
+    const MAX_ENTRIES: usize = 32;
+    // 32 (pubkey) + vec of up to MAX_ENTRIES entries
+    const SPACE: usize = 32 + 4 + HistoryEntry::SPACE * Self::MAX_ENTRIES;
+
+    fn record(&mut self, field: EventField, timestamp: i64, editor: Pubkey) -> Result<()> {
+        require!(
+            self.entries.len() < Self::MAX_ENTRIES,
+            ErrorCode::HistoryFull
+        );
+        self.entries.push(HistoryEntry {
+            field,
+            timestamp,
+            editor,
+        });
         Ok(())
     }
+}
 
-    /// Join an existing event
-    pub fn join_event(ctx: Context<JoinEvent>, event_id: String) -> Result<()> {
-        let event = &mut ctx.accounts.event;
-        let claim = &mut ctx.accounts.claim;
-        let attendee = &ctx.accounts.attendee;
+#[account]
+#[cfg_attr(test, derive(Default))]
+pub struct AttendeeClaim {
+    pub attendee: Pubkey,
+    pub timestamp: i64,
+    /// Slot the claim was made in; orders joins that share a `timestamp`
+    pub slot: u64,
+    /// Unix time the claim lapses at; 0 = never
+    pub expires_at: i64,
+    /// Unique per event, for printing on tickets and badges
+    pub serial: u32,
+    /// Whether the attendee acknowledged the event agenda when joining
+    pub acknowledged: bool,
+    /// Hash of the attendee's off-chain feedback comment
+    pub feedback_hash: Option<[u8; 32]>,
+    /// 1-based join order: `claims_count` right after this claim was admitted.
+    /// 0 for claims migrated from the baseline layout, which had no order.
+    pub position: u16,
+    /// hash(event, attendee, position, slot)
+    pub position_commitment: [u8; 32],
+    /// `ATTENDANCE_IN_PERSON` or `ATTENDANCE_VIRTUAL`
+    pub attendance_type: u8,
+    /// Guests registered under this claim, see `GuestClaim`
+    pub guests_registered: u8,
+    /// Index into `Event::channels` the claim was registered through
+    pub channel: Option<u8>,
+    /// Created by `import_claims` from an off-chain record, not by a join
+    pub imported: bool,
+    /// Creator whose `AttendeeProfile` the join was credited to. Kept so a
+    /// sweep after an ownership transfer takes it off the same profile.
+    pub credited_creator: Option<Pubkey>,
+}
 
-        // Verify event exists (this is implicit since we're using the event as an account)
-        
-        // Check if max claims has been reached
+impl AttendeeClaim {
+    const SPACE: usize = 32 + // attendee
+        8 + 8 + 8 +           // timestamp, slot and expires_at
+        4 + 1 +               // serial and acknowledged
+        1 + 32 +              // feedback_hash
+        2 + 32 +              // position and position_commitment
+        1 + 1 +               // attendance_type and guests_registered
+        1 + 1 +               // channel
+        1 +                   // imported
+        1 + 32; // credited_creator
+
+    /// Deserialize a claim owned by this program, or `None` if the account
+    /// isn't one (uninitialized, owned elsewhere, or another account type)
+    fn try_load(info: &AccountInfo, program_id: &Pubkey) -> Option<AttendeeClaim> {
+        if info.owner != program_id || info.data_is_empty() {
+            return None;
+        }
+        let data = info.try_borrow_data().ok()?;
+        AttendeeClaim::try_deserialize(&mut &data[..]).ok()
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+}
+
+/// Discriminator of the original `Claim` account, `sha256("account:Claim")`,
+/// which claims created before `AttendeeClaim` still carry
+const LEGACY_CLAIM_DISCRIMINATOR: [u8; 8] = [155, 70, 22, 176, 123, 215, 246, 102];
+
+/// A claim in the original `Claim` layout, read only by `migrate_claim`.
+/// The baseline layout ends at `timestamp`, followed by zero padding; the
+/// fields after it are read when the data has them and default otherwise.
+#[derive(Default)]
+struct LegacyClaim {
+    attendee: Pubkey,
+    event_id: String,
+    timestamp: i64,
+    slot: u64,
+    expires_at: i64,
+    serial: u32,
+    acknowledged: bool,
+    feedback_hash: Option<[u8; 32]>,
+    position: u16,
+    position_commitment: [u8; 32],
+    attendance_type: u8,
+    guests_registered: u8,
+    channel: Option<u8>,
+}
+
+impl LegacyClaim {
+    fn decode(data: &[u8]) -> Option<LegacyClaim> {
+        if data.get(..8) != Some(&LEGACY_CLAIM_DISCRIMINATOR[..]) {
+            return None;
+        }
+        let buf = &mut &data[8..];
+        Some(LegacyClaim {
+            attendee: AnchorDeserialize::deserialize(buf).ok()?,
+            event_id: AnchorDeserialize::deserialize(buf).ok()?,
+            timestamp: AnchorDeserialize::deserialize(buf).ok()?,
+            slot: read_or_default(buf),
+            expires_at: read_or_default(buf),
+            serial: read_or_default(buf),
+            acknowledged: read_or_default(buf),
+            feedback_hash: read_or_default(buf),
+            position: read_or_default(buf),
+            position_commitment: read_or_default(buf),
+            attendance_type: read_or_default(buf),
+            guests_registered: read_or_default(buf),
+            channel: read_or_default(buf),
+        })
+    }
+}
+
+/// Read the next field of a `LegacyClaim`, or its default once the data runs
+/// out. A short read empties `buf`, so every later field defaults too.
+fn read_or_default<T: AnchorDeserialize + Default>(buf: &mut &[u8]) -> T {
+    match T::deserialize(buf) {
+        Ok(value) => value,
+        Err(_) => {
+            *buf = &[];
+            T::default()
+        }
+    }
+}
+
+/// One historical attendee for `import_claims`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ImportEntry {
+    pub attendee: Pubkey,
+    pub timestamp: i64,
+}
+
+/// A ticket distribution channel with its own allocation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Channel {
+    pub id: String,
+    pub cap: u16,
+    pub used: u16,
+}
+
+impl Channel {
+    const MAX_CHANNELS: usize = 4;
+    const MAX_ID_LEN: usize = 16;
+    // id (max 16 chars) + 2 + 2 (u16)
+    const SPACE: usize = 4 + Self::MAX_ID_LEN + 2 + 2;
+}
+
+/// Defaults an `EventTemplate` applies to new events
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TemplateSettings {
+    pub description: String,
+    pub venue: String,
+    pub max_claims: u16,
+    pub unlimited: bool,
+    pub is_virtual: bool,
+    pub stream_uri: String,
+    pub full_redirect_uri: Option<String>,
+    pub claim_ttl_secs: i64,
+    pub creator_pays_rent: bool,
+    pub attestation_authority: Option<Pubkey>,
+}
+
+impl TemplateSettings {
+    const SPACE: usize = 4 + 200 + // description: max 200 chars
+        4 + 100 +                  // venue: max 100 chars
+        2 + 1 + 1 +                // max_claims, unlimited and is_virtual
+        4 + 100 +                  // stream_uri: max 100 chars
+        1 + 4 + 100 +              // full_redirect_uri: optional, max 100 chars
+        8 + 1 +                    // claim_ttl_secs and creator_pays_rent
+        1 + 32; // attestation_authority
+
+    /// Same rules `create_event` applies to these fields
+    fn validate(&self) -> Result<()> {
+        require!(self.description.len() <= 200, ErrorCode::DescriptionTooLong);
+        require!(self.venue.len() <= 100, ErrorCode::VenueTooLong);
         require!(
-            event.claims_count < event.max_claims,
-            ErrorCode::MaxClaimsReached
+            self.max_claims > 0 || self.unlimited,
+            ErrorCode::InvalidMaxClaims
         );
+        if let Some(uri) = &self.full_redirect_uri {
+            require!(uri.len() <= 100, ErrorCode::RedirectUriTooLong);
+        }
+        require!(self.claim_ttl_secs >= 0, ErrorCode::InvalidClaimTtl);
+        require!(self.stream_uri.len() <= 100, ErrorCode::StreamUriTooLong);
+        if self.is_virtual {
+            require!(!self.stream_uri.is_empty(), ErrorCode::StreamUriRequired);
+        } else {
+            require!(!self.venue.is_empty(), ErrorCode::VenueRequired);
+        }
+        Ok(())
+    }
+}
 
-        // Set claim data
-        claim.attendee = attendee.key();
-        claim.event_id = event_id;
-        claim.timestamp = Clock::get()?.unix_timestamp;
+#[account]
+pub struct EventTemplate {
+    pub creator: Pubkey,
+    pub template_id: String,
+    pub settings: TemplateSettings,
+}
 
-        // Increment claims count
-        event.claims_count += 1;
+impl EventTemplate {
+    // 32 (pubkey) + template_id (max 16 chars) + settings
+    const SPACE: usize = 32 + 4 + 16 + TemplateSettings::SPACE;
 
-        msg!("New attendee joined event: {}", event.event_id);
-        Ok(())
+    fn try_load(info: &AccountInfo, program_id: &Pubkey) -> Option<EventTemplate> {
+        if info.owner != program_id || info.data_is_empty() {
+            return None;
+        }
+        let data = info.try_borrow_data().ok()?;
+        EventTemplate::try_deserialize(&mut &data[..]).ok()
     }
 }
 
-#[derive(Accounts)]
-#[instruction(event_id: String)]
-pub struct CreateEvent<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = 8 + Event::space(&event_id),
-        seeds = [b"event", event_id.as_bytes()],
-        bump
-    )]
-    pub event: Account<'info, Event>,
-    
-    #[account(mut)]
-    pub creator: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+/// Number of events created on one day (`unix_timestamp / 86400`)
+#[account]
+pub struct DailyStats {
+    pub day: u64,
+    pub events_created: u32,
 }
 
-#[derive(Accounts)]
-#[instruction(event_id: String)]
-pub struct JoinEvent<'info> {
-    #[account(
-        mut,
-        seeds = [b"event", event_id.as_bytes()],
-        bump,
-        constraint = event.claims_count < event.max_claims @ ErrorCode::MaxClaimsReached,
-    )]
-    pub event: Account<'info, Event>,
-    
-    #[account(
-        init,
-        payer = attendee,
-        space = 8 + Claim::space(&event_id),
-        seeds = [b"claim", event_id.as_bytes(), attendee.key().as_ref()],
-        bump,
-        constraint = event.event_id == event_id @ ErrorCode::EventIdMismatch,
-    )]
-    pub claim: Account<'info, Claim>,
-    
-    #[account(mut)]
-    pub attendee: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+impl DailyStats {
+    const SPACE: usize = 8 + 4;
+
+    fn current_day() -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+        Ok(u64::try_from(now / 86_400).unwrap_or_default())
+    }
 }
 
+/// An attendee's history with one creator, seeded by
+/// `["profile", creator, attendee]`
 #[account]
-pub struct Event {
+pub struct AttendeeProfile {
     pub creator: Pubkey,
-    pub event_id: String,
-    pub name: String,
-    pub description: String,
-    pub venue: String,
-    pub date: String,
-    pub max_claims: u16,
-    pub claims_count: u16,
+    pub attendee: Pubkey,
+    /// Events of this creator the attendee holds a joined claim for;
+    /// swept claims are taken back off and imported claims never count
+    pub events_attended: u32,
+    /// `BADGE_*`, derived from `events_attended`
+    pub badge_level: u8,
 }
 
-impl Event {
-    fn space(event_id: &str) -> usize {
-        // 32 (pubkey) + sizes of strings + 2 + 2 (u16) + padding
-        32 + 
-        4 + event_id.len() + 
-        4 + 50 +  // name: max 50 chars
-        4 + 200 + // description: max 200 chars
-        4 + 100 + // venue: max 100 chars
-        4 + 30 +  // date: max 30 chars
-        2 + 2 +   // max_claims and claims_count
-        100       // some padding
+impl AttendeeProfile {
+    // 32 + 32 (pubkeys) + 4 (u32) + 1 (u8)
+    const SPACE: usize = 32 + 32 + 4 + 1;
+
+    /// Count one more of the creator's events and recompute the badge
+    fn record_join(&mut self, creator: Pubkey, attendee: Pubkey) {
+        self.creator = creator;
+        self.attendee = attendee;
+        self.events_attended = self.events_attended.saturating_add(1);
+        self.badge_level = Self::badge_for(self.events_attended);
+    }
+
+    /// Take back a join whose claim was swept and recompute the badge
+    fn record_leave(&mut self) {
+        self.events_attended = self.events_attended.saturating_sub(1);
+        self.badge_level = Self::badge_for(self.events_attended);
+    }
+
+    /// Highest badge whose threshold `events_attended` has reached
+    fn badge_for(events_attended: u32) -> u8 {
+        BADGE_THRESHOLDS
+            .iter()
+            .filter(|&&threshold| events_attended >= threshold)
+            .count() as u8
+    }
+
+    fn try_load(info: &AccountInfo, program_id: &Pubkey) -> Option<AttendeeProfile> {
+        if info.owner != program_id || info.data_is_empty() {
+            return None;
+        }
+        let data = info.try_borrow_data().ok()?;
+        AttendeeProfile::try_deserialize(&mut &data[..]).ok()
     }
 }
 
+/// A guest registered by an attendee, seeded by
+/// `["guest", event_id, host, serial]` with `serial` as little-endian bytes,
+/// so a host who re-joins never collides with their earlier guests
 #[account]
-pub struct Claim {
+pub struct GuestClaim {
+    pub event: Pubkey,
+    pub host: Pubkey,
+    pub guest_name: String,
+    pub timestamp: i64,
+    pub serial: u32,
+}
+
+impl GuestClaim {
+    // 32 + 32 (pubkeys) + guest_name (max 50 chars) + 8 (i64) + 4 (u32)
+    const SPACE: usize = 32 + 32 + 4 + 50 + 8 + 4;
+
+    fn try_load(info: &AccountInfo, program_id: &Pubkey) -> Option<GuestClaim> {
+        if info.owner != program_id || info.data_is_empty() {
+            return None;
+        }
+        let data = info.try_borrow_data().ok()?;
+        GuestClaim::try_deserialize(&mut &data[..]).ok()
+    }
+}
+
+/// Emitted when a join is rejected because the event is full
+#[event]
+pub struct EventFull {
+    pub event_id: String,
+    pub redirect_uri: Option<String>,
+}
+
+/// Emitted when a join brings an event to one of `MILESTONES` attendees
+#[event]
+pub struct MilestoneReached {
+    pub event_id: String,
+    pub milestone: u32,
+}
+
+#[event]
+pub struct AccessTokenIssued {
+    pub event_id: String,
+    pub attendee: Pubkey,
+    pub expires_at: i64,
+    pub nonce: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AccessToken {
+    pub event_id: String,
     pub attendee: Pubkey,
+    pub expires_at: i64,
+    /// Server-issued nonce the token is bound to
+    pub nonce: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreatorTotals {
+    pub total_events: u32,
+    pub total_attendees: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProgramInfo {
+    pub program_id: Pubkey,
+    pub account_version: u8,
+    /// Bitwise OR of the `FEATURE_*` constants
+    pub supported_features: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct JoinEligibility {
+    pub can_join: bool,
+    /// Anchor error code of the first failing check; 0 when `can_join`.
+    /// `AlreadyJoined` has no counterpart in `join_event`: there a second
+    /// join fails earlier, when `init` finds the claim account already in
+    /// use, with the system program's error instead.
+    pub reason_code: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimStatus {
+    pub exists: bool,
+    /// Join time; 0 when `exists` is false
+    pub timestamp: i64,
+}
+
+/// Emitted for every compressed claim so indexers can rebuild the tree
+#[event]
+pub struct CompressedClaimAdded {
     pub event_id: String,
+    pub attendee: Pubkey,
     pub timestamp: i64,
+    pub index: u32,
 }
 
-impl Claim {
-    fn space(event_id: &str) -> usize {
-        // 32 (pubkey) + size of event_id string + 8 (i64) + padding
-        32 + 4 + event_id.len() + 8 + 50
-    }
+/// Portable proof of a claim; `creator` is the authority expected to sign it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimProof {
+    pub event_id: String,
+    pub attendee: Pubkey,
+    pub timestamp: i64,
+    pub creator: Pubkey,
 }
 
 #[error_code]
@@ -172,4 +3592,550 @@ pub enum ErrorCode {
     EventIdMismatch,
     #[msg("Attendee has already joined this event")]
     AlreadyJoined,
+    #[msg("Only the event creator can do this")]
+    Unauthorized,
+    #[msg("Organizer memo must be 100 characters or less")]
+    MemoTooLong,
+    #[msg("Redirect URI must be 100 characters or less")]
+    RedirectUriTooLong,
+    #[msg("Ticket number is not on the allowlist")]
+    TicketNotAllowed,
+    #[msg("Ticket number has already been used")]
+    TicketAlreadyUsed,
+    #[msg("Allowlist bitmap is too large")]
+    AllowlistTooLarge,
+    #[msg("Event has been archived")]
+    EventArchived,
+    #[msg("Claim TTL must not be negative")]
+    InvalidClaimTtl,
+    #[msg("Claim has expired")]
+    ClaimExpired,
+    #[msg("Claim count does not match the provided claim accounts")]
+    ClaimCountMismatch,
+    #[msg("A claim on the parent event is required to join this track")]
+    ParentClaimRequired,
+    #[msg("In-person events require a venue")]
+    VenueRequired,
+    #[msg("Virtual events require a stream URI")]
+    StreamUriRequired,
+    #[msg("Stream URI must be 100 characters or less")]
+    StreamUriTooLong,
+    #[msg("Only the creator of a sponsored event can pay for another attendee")]
+    SponsorshipNotEnabled,
+    #[msg("Event ID must not be empty or contain whitespace or control characters")]
+    InvalidEventId,
+    #[msg("Event has run out of serial numbers")]
+    SerialOverflow,
+    #[msg("Agenda hash does not match the event agenda")]
+    AgendaNotAcknowledged,
+    #[msg("Event metadata is locked")]
+    MetadataLocked,
+    #[msg("Attendee is blocked from this event")]
+    AttendeeBlocked,
+    #[msg("Blocklist is full")]
+    BlocklistFull,
+    #[msg("Join must be co-signed by the event's attestation authority")]
+    MissingAttestation,
+    #[msg("Claim tree depth must be between 1 and 20")]
+    InvalidTreeDepth,
+    #[msg("Event already has claims")]
+    EventHasClaims,
+    #[msg("Claim tree is full")]
+    ClaimTreeFull,
+    #[msg("Event uses compressed claims")]
+    CompressedClaimsOnly,
+    #[msg("Capacity cannot be set below the current number of claims")]
+    CannotShrinkBelowClaims,
+    #[msg("Feedback has already been submitted for this claim")]
+    AlreadySubmittedFeedback,
+    #[msg("No ownership transfer is pending")]
+    NoPendingOwner,
+    #[msg("Signer is not the pending owner")]
+    NotPendingOwner,
+    #[msg("Event history is full; no further detail changes can be recorded")]
+    HistoryFull,
+    #[msg("Attendance type must be in-person or virtual")]
+    InvalidAttendanceType,
+    #[msg("In-person capacity has been reached")]
+    InPersonCapReached,
+    #[msg("Virtual capacity has been reached")]
+    VirtualCapReached,
+    #[msg("Too many entries for a single batch")]
+    BatchTooLarge,
+    #[msg("Batch accounts do not match the listed attendees")]
+    BatchAccountsMismatch,
+    #[msg("Template ID must be between 1 and 16 characters")]
+    InvalidTemplateId,
+    #[msg("Template not found")]
+    TemplateNotFound,
+    #[msg("Attendee has registered all the guests they are allowed")]
+    GuestAllowanceExceeded,
+    #[msg("Guest name must be 50 characters or less")]
+    GuestNameTooLong,
+    #[msg("Unknown or missing registration channel")]
+    InvalidChannel,
+    #[msg("Registration channel is full")]
+    ChannelFull,
+    #[msg("Event already has the maximum number of channels")]
+    TooManyChannels,
+    #[msg("Account is not a claim in the legacy layout")]
+    NotLegacyClaim,
+    #[msg("Basis points value is out of range")]
+    InvalidBps,
+    #[msg("Display currency must be 8 characters or less")]
+    DisplayCurrencyTooLong,
+    #[msg("Join must be co-signed by the event's age verifier")]
+    AgeNotVerified,
+    #[msg("Claim can no longer be changed")]
+    ClaimImmutable,
+    #[msg("Immutability window cannot be negative")]
+    InvalidImmutabilityWindow,
+    #[msg("Imported claim timestamp is in the future")]
+    ImportTimestampInFuture,
+    #[msg("Pass the claim accounts to reconcile an event with caps or channels")]
+    ClaimAccountsRequired,
+    #[msg("Immutability window can only be shortened")]
+    ImmutabilityWindowLoosened,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        hashv(&[left.as_ref(), right.as_ref()]).to_bytes()
+    }
+
+    fn claim_tree(depth: u8) -> ClaimTree {
+        ClaimTree {
+            event: Pubkey::default(),
+            depth,
+            next_index: 0,
+            filled_subtrees: vec![[0; 32]; depth as usize],
+        }
+    }
+
+    #[test]
+    fn allowlist_consumes_each_issued_ticket_once() {
+        let mut allowlist = Allowlist {
+            event: Pubkey::default(),
+            allowlist_bitmap: vec![0b0000_0101],
+            consumed_bitmap: vec![0],
+        };
+
+        allowlist.consume(0).unwrap();
+        allowlist.consume(2).unwrap();
+        assert_eq!(
+            allowlist.consume(0).unwrap_err(),
+            ErrorCode::TicketAlreadyUsed.into()
+        );
+        assert_eq!(
+            allowlist.consume(1).unwrap_err(),
+            ErrorCode::TicketNotAllowed.into()
+        );
+        assert_eq!(
+            allowlist.consume(8).unwrap_err(),
+            ErrorCode::TicketNotAllowed.into()
+        );
+        assert_eq!(allowlist.consumed_bitmap, vec![0b0000_0101]);
+    }
+
+    #[test]
+    fn capacity_adds_overbooking_allowance() {
+        let mut event = Event {
+            max_claims: 100,
+            ..Default::default()
+        };
+        assert_eq!(event.capacity(), 100);
+
+        event.overbook_bps = 1_500;
+        assert_eq!(event.capacity(), 115);
+
+        // Fractional seats round down
+        event.max_claims = 7;
+        assert_eq!(event.capacity(), 8);
+
+        event.max_claims = u16::MAX;
+        event.overbook_bps = MAX_OVERBOOK_BPS;
+        assert_eq!(event.capacity(), u16::MAX);
+    }
+
+    #[test]
+    fn badge_follows_thresholds() {
+        assert_eq!(AttendeeProfile::badge_for(0), BADGE_NONE);
+        assert_eq!(AttendeeProfile::badge_for(2), BADGE_NONE);
+        assert_eq!(AttendeeProfile::badge_for(3), BADGE_BRONZE);
+        assert_eq!(AttendeeProfile::badge_for(10), BADGE_SILVER);
+        assert_eq!(AttendeeProfile::badge_for(24), BADGE_SILVER);
+        assert_eq!(AttendeeProfile::badge_for(25), BADGE_GOLD);
+        assert_eq!(AttendeeProfile::badge_for(u32::MAX), BADGE_GOLD);
+    }
+
+    #[test]
+    fn swept_join_is_taken_back() {
+        let (creator, attendee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut profile = AttendeeProfile {
+            creator,
+            attendee,
+            events_attended: 2,
+            badge_level: BADGE_NONE,
+        };
+
+        profile.record_join(creator, attendee);
+        assert_eq!(profile.badge_level, BADGE_BRONZE);
+
+        profile.record_leave();
+        assert_eq!(profile.events_attended, 2);
+        assert_eq!(profile.badge_level, BADGE_NONE);
+
+        profile.events_attended = 0;
+        profile.record_leave();
+        assert_eq!(profile.events_attended, 0);
+    }
+
+    #[test]
+    fn attendee_claim_space_fits_largest_claim_and_shrank() {
+        let claim = AttendeeClaim {
+            attendee: Pubkey::new_unique(),
+            timestamp: 1,
+            slot: 2,
+            expires_at: 3,
+            serial: 4,
+            acknowledged: true,
+            feedback_hash: Some([5; 32]),
+            position: 6,
+            position_commitment: [7; 32],
+            attendance_type: ATTENDANCE_VIRTUAL,
+            guests_registered: 8,
+            channel: Some(9),
+            imported: true,
+            credited_creator: Some(Pubkey::new_unique()),
+        };
+        assert_eq!(claim.try_to_vec().unwrap().len(), AttendeeClaim::SPACE);
+
+        // `Claim::space` of the layout it replaced, for the shortest ID
+        let event_id = "e";
+        let legacy_space =
+            32 + 4 + event_id.len() + 8 + 8 + 8 + 4 + 1 + 1 + 32 + 2 + 32 + 1 + 1 + 1 + 1 + 50;
+        assert!(AttendeeClaim::SPACE < legacy_space);
+    }
+
+    #[test]
+    fn baseline_claim_decodes_with_defaults() {
+        let attendee = Pubkey::new_unique();
+        let mut data = LEGACY_CLAIM_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(attendee.as_ref());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"demo");
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&[0; 50]);
+
+        let legacy = LegacyClaim::decode(&data).unwrap();
+        assert_eq!(legacy.attendee, attendee);
+        assert_eq!(legacy.event_id, "demo");
+        assert_eq!(legacy.timestamp, 1_700_000_000);
+        assert_eq!(legacy.serial, 0);
+        assert_eq!(legacy.channel, None);
+
+        // Cut short right after the timestamp, as an account without the
+        // padding would be
+        let legacy = LegacyClaim::decode(&data[..data.len() - 50]).unwrap();
+        assert_eq!(legacy.timestamp, 1_700_000_000);
+        assert_eq!(legacy.position_commitment, [0; 32]);
+
+        data[0] ^= 1;
+        assert!(LegacyClaim::decode(&data).is_none());
+    }
+
+    #[test]
+    fn claim_address_resolves_without_stored_event_id() {
+        let attendee = Pubkey::new_unique();
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"claim", b"demo".as_ref(), attendee.as_ref()],
+            &crate::ID,
+        );
+        assert_eq!(claim_address("demo", &attendee, &crate::ID), expected);
+        assert_ne!(claim_address("demo2", &attendee, &crate::ID), expected);
+    }
+
+    #[test]
+    fn milestone_is_announced_once() {
+        let mut event = Event {
+            max_claims: 30,
+            ..Default::default()
+        };
+        for _ in 0..24 {
+            event.admit().unwrap();
+        }
+        assert_eq!(event.milestone_reached, 0);
+
+        event.admit().unwrap();
+        assert_eq!(event.milestone_reached, 25);
+
+        // Dropping back below and crossing again doesn't re-announce
+        event.claims_count = 24;
+        event.admit().unwrap();
+        assert_eq!(event.claims_count, 25);
+        assert_eq!(event.milestone_reached, 25);
+    }
+
+    #[test]
+    fn claims_remaining_never_underflows() {
+        let mut event = Event {
+            max_claims: 10,
+            claims_count: 4,
+            ..Default::default()
+        };
+        assert_eq!(event.claims_remaining(), 6);
+
+        // Only reachable through a miscount, but must not wrap
+        event.claims_count = 12;
+        assert_eq!(event.claims_remaining(), 0);
+
+        event.unlimited = true;
+        assert_eq!(event.claims_remaining(), u32::from(u16::MAX) - 12);
+    }
+
+    #[test]
+    fn event_id_validation() {
+        assert!(validate_event_id("demo-2024").is_ok());
+        for id in ["", " ", "my event", "tab\tid", "line\nid", "bell\u{7}"] {
+            assert_eq!(
+                validate_event_id(id).unwrap_err(),
+                ErrorCode::InvalidEventId.into()
+            );
+        }
+        assert_eq!(
+            validate_event_id("seventeen-chars!!").unwrap_err(),
+            ErrorCode::EventIdTooLong.into()
+        );
+
+        // IDs are case-sensitive, so these are two different events
+        assert!(validate_event_id("Demo").is_ok());
+        let address =
+            |id: &str| Pubkey::find_program_address(&[b"event", id.as_bytes()], &crate::ID).0;
+        assert_ne!(address("Demo"), address("demo"));
+    }
+
+    #[test]
+    fn history_keeps_order_and_fills_up() {
+        let editor = Pubkey::new_unique();
+        let mut history = EventHistory {
+            event: Pubkey::default(),
+            entries: Vec::new(),
+        };
+
+        history.record(EventField::Venue, 1, editor).unwrap();
+        history.record(EventField::Date, 2, editor).unwrap();
+        assert!(history.entries[0].field == EventField::Venue);
+        assert!(history.entries[1].field == EventField::Date);
+        assert_eq!(history.entries[1].timestamp, 2);
+
+        for timestamp in 3..=EventHistory::MAX_ENTRIES as i64 {
+            history.record(EventField::Name, timestamp, editor).unwrap();
+        }
+        assert_eq!(
+            history.record(EventField::Name, 0, editor).unwrap_err(),
+            ErrorCode::HistoryFull.into()
+        );
+        assert_eq!(history.entries.len(), EventHistory::MAX_ENTRIES);
+    }
+
+    #[test]
+    fn zero_cap_needs_unlimited() {
+        let create = |max_claims: u16, unlimited: bool| {
+            init_event(
+                &mut Event::default(),
+                Pubkey::new_unique(),
+                "demo".to_string(),
+                "Demo".to_string(),
+                String::new(),
+                "Hall A".to_string(),
+                "2024-01-01".to_string(),
+                max_claims,
+                None,
+                0,
+                false,
+                String::new(),
+                unlimited,
+            )
+        };
+
+        assert_eq!(
+            create(0, false).unwrap_err(),
+            ErrorCode::InvalidMaxClaims.into()
+        );
+        assert!(create(0, true).is_ok());
+        assert!(create(100, false).is_ok());
+        assert!(create(100, true).is_ok());
+    }
+
+    #[test]
+    fn attendance_type_caps_and_release() {
+        let mut event = Event {
+            max_claims: 10,
+            in_person_cap: 1,
+            ..Default::default()
+        };
+
+        event.admit_attendance_type(ATTENDANCE_IN_PERSON).unwrap();
+        assert_eq!(
+            event
+                .admit_attendance_type(ATTENDANCE_IN_PERSON)
+                .unwrap_err(),
+            ErrorCode::InPersonCapReached.into()
+        );
+        // No virtual cap, so only `max_claims` bounds virtual seats
+        event.admit_attendance_type(ATTENDANCE_VIRTUAL).unwrap();
+        event.admit_attendance_type(ATTENDANCE_VIRTUAL).unwrap();
+        assert_eq!(
+            event.admit_attendance_type(2).unwrap_err(),
+            ErrorCode::InvalidAttendanceType.into()
+        );
+
+        event.claims_count = 3;
+        event.release(&AttendeeClaim {
+            attendance_type: ATTENDANCE_IN_PERSON,
+            ..Default::default()
+        });
+        assert_eq!(
+            (
+                event.claims_count,
+                event.in_person_count,
+                event.virtual_count
+            ),
+            (2, 0, 2)
+        );
+        event.admit_attendance_type(ATTENDANCE_IN_PERSON).unwrap();
+    }
+
+    #[test]
+    fn channels_cap_their_own_seats() {
+        let mut event = Event::default();
+        assert_eq!(event.admit_channel(None).unwrap(), None);
+        assert_eq!(
+            event.admit_channel(Some("partner")).unwrap_err(),
+            ErrorCode::InvalidChannel.into()
+        );
+
+        event.channels = vec![
+            Channel {
+                id: "public".to_string(),
+                cap: 5,
+                used: 0,
+            },
+            Channel {
+                id: "partner".to_string(),
+                cap: 1,
+                used: 0,
+            },
+        ];
+        assert_eq!(
+            event.admit_channel(None).unwrap_err(),
+            ErrorCode::InvalidChannel.into()
+        );
+        assert_eq!(event.admit_channel(Some("partner")).unwrap(), Some(1));
+        assert_eq!(
+            event.admit_channel(Some("partner")).unwrap_err(),
+            ErrorCode::ChannelFull.into()
+        );
+        assert_eq!(event.admit_channel(Some("public")).unwrap(), Some(0));
+
+        event.release(&AttendeeClaim {
+            channel: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(event.channels[1].used, 0);
+        assert_eq!(event.admit_channel(Some("partner")).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn mode_flags_follow_settings() {
+        let mut event = Event::default();
+        event.refresh_mode_flags();
+        assert_eq!(event.mode_flags, 0);
+
+        event.is_virtual = true;
+        event.unlimited = true;
+        event.refresh_mode_flags();
+        assert_eq!(event.mode_flags, MODE_VIRTUAL | MODE_UNLIMITED);
+
+        event.is_virtual = false;
+        event.parent_event = Some(Pubkey::new_unique());
+        event.claim_ttl_secs = 3_600;
+        event.virtual_cap = 10;
+        event.immutable_after_secs = 60;
+        event.refresh_mode_flags();
+        assert_eq!(
+            event.mode_flags,
+            MODE_UNLIMITED
+                | MODE_TRACK
+                | MODE_CLAIM_EXPIRY
+                | MODE_HYBRID_CAPS
+                | MODE_IMMUTABLE_CLAIMS
+        );
+    }
+
+    #[test]
+    fn empty_root_hashes_zero_leaves() {
+        let zero = [0; 32];
+        assert_eq!(ClaimTree::empty_root(0), zero);
+        assert_eq!(
+            ClaimTree::empty_root(2),
+            node(node(zero, zero), node(zero, zero))
+        );
+    }
+
+    #[test]
+    fn append_matches_full_tree_root() {
+        let zero = [0; 32];
+        let leaves = [[1; 32], [2; 32], [3; 32], [4; 32]];
+        let mut tree = claim_tree(2);
+
+        let root = tree.append(leaves[0]).unwrap();
+        assert_eq!(root, node(node(leaves[0], zero), node(zero, zero)));
+
+        tree.append(leaves[1]).unwrap();
+        tree.append(leaves[2]).unwrap();
+        let root = tree.append(leaves[3]).unwrap();
+        assert_eq!(
+            root,
+            node(node(leaves[0], leaves[1]), node(leaves[2], leaves[3]))
+        );
+        assert_eq!(tree.next_index, 4);
+        assert!(tree.append([5; 32]).is_err());
+    }
+
+    #[test]
+    fn proof_rebuilds_root_for_each_leaf() {
+        let leaves = [[1; 32], [2; 32], [3; 32], [4; 32]];
+        let mut tree = claim_tree(2);
+        let mut root = [0; 32];
+        for leaf in leaves {
+            root = tree.append(leaf).unwrap();
+        }
+
+        let left = node(leaves[0], leaves[1]);
+        let right = node(leaves[2], leaves[3]);
+        assert_eq!(
+            merkle_root_from_proof(leaves[0], 0, &[leaves[1], right]),
+            root
+        );
+        assert_eq!(
+            merkle_root_from_proof(leaves[1], 1, &[leaves[0], right]),
+            root
+        );
+        assert_eq!(
+            merkle_root_from_proof(leaves[2], 2, &[leaves[3], left]),
+            root
+        );
+        assert_eq!(
+            merkle_root_from_proof(leaves[3], 3, &[leaves[2], left]),
+            root
+        );
+        assert_ne!(
+            merkle_root_from_proof(leaves[3], 2, &[leaves[2], left]),
+            root
+        );
+    }
 }
@@ -367,6 +367,7 @@ async def create_event_onchain(
         # First try with deployed program
         program = await initialize_program()
         event_account = f"event_{event_id}"
+        daily_stats_account = f"daily_{int(datetime.now().timestamp()) // 86400}"
         
         # Add timeout handling to prevent hanging
         import asyncio
@@ -376,12 +377,18 @@ async def create_event_onchain(
             return await program.rpc["createEvent"](
                 event_id,
                 name,
-                venue, 
                 description,
+                venue,
                 date_str,
                 max_claims,
+                None,  # full_redirect_uri
+                0,  # claim_ttl_secs: claims never expire
+                False,  # is_virtual
+                "",  # stream_uri
+                False,  # unlimited
                 ctx={"accounts": {
                     "event": event_account,
+                    "dailyStats": daily_stats_account,
                     "creator": creator_wallet,
                     "systemProgram": "11111111111111111111111111111111"
                 }}
@@ -586,6 +593,7 @@ async def join_event_onchain(
         # Generate account names (would be PDAs in real implementation)
         event_account = f"event_{event_id}"
         claim_account = f"claim_{event_id}_{attendee_wallet[:8]}"
+        profile_account = f"profile_{attendee_wallet[:8]}"
         
         # Add timeout handling to prevent hanging
         import asyncio
@@ -593,10 +601,16 @@ async def join_event_onchain(
         async def join_with_timeout():
             return await program.rpc["joinEvent"](
                 event_id,
+                None,  # ticket_number
+                None,  # agenda_hash
+                0,  # attendance_type: in person
+                None,  # channel_id
                 ctx={"accounts": {
                     "event": event_account,
                     "claim": claim_account,
+                    "profile": profile_account,
                     "attendee": attendee_wallet,
+                    "payer": attendee_wallet,
                     "systemProgram": "11111111111111111111111111111111"
                 }}
             )